@@ -4,10 +4,12 @@ use quote::ToTokens;
 use syn::parse_macro_input;
 
 use crate::load_test_function::LoadTestFunction;
+use crate::metric_derive::MetricDerive;
 use crate::scenario::Scenario;
 use crate::scenario_arguments::ScenarioArguments;
 
 mod load_test_function;
+mod metric_derive;
 mod scenario;
 mod scenario_arguments;
 
@@ -20,3 +22,15 @@ pub fn scenario(args: TokenStream, item: TokenStream) -> TokenStream {
 
     scenario.to_token_stream().into()
 }
+
+/// Implements `Metric` for an enum with unit variants
+///
+/// Metric names are variant names in snake case, which can be overridden
+/// with `#[metric(name = "...")]` on a variant. `Clone`, `Copy`, `PartialEq`, `Eq`
+/// and `Hash` required by `Metric` are expected to be derived as usual, or implemented
+/// by the macro when `#[metric(bounds)]` is specified on the enum. `#[metric(all)]` adds
+/// `ALL` constant with every variant in order of declaration.
+#[proc_macro_derive(Metric, attributes(metric))]
+pub fn derive_metric(item: TokenStream) -> TokenStream {
+    parse_macro_input!(item as MetricDerive).to_token_stream().into()
+}
@@ -7,12 +7,23 @@ use syn::{
     ReturnType, Signature, spanned::Spanned, TraitBound, Type, TypeParamBound, TypePath,
 };
 
-use crate::scenario_arguments::ScenarioArguments;
+use crate::scenario_arguments::{ScenarioArguments, ScenarioHooks};
+
+/// Ownership of a state argument of the load test function
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub(crate) enum StateKind {
+    /// `&mut T` argument, created for each virtual user
+    User,
+    /// `&T` argument, created once in the builder and shared between users
+    Shared,
+}
+
+type StateArgument = (Ident, Path, StateKind);
 
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) struct LoadTestFunction {
     function: ItemFn,
-    state: Vec<(Ident, Path)>,
+    state: Vec<StateArgument>,
     metric_type: Type,
 }
 
@@ -40,9 +51,66 @@ impl Parse for LoadTestFunction {
 }
 
 impl LoadTestFunction {
-    fn builder_definition(&self, name: &Ident) -> TokenStream {
-        quote! {
-            struct #name;
+    fn state_of_kind(&self, kind: StateKind) -> impl Iterator<Item = (&Ident, &Path)> {
+        self.state
+            .iter()
+            .filter(move |(_, _, state_kind)| *state_kind == kind)
+            .map(|(ident, path, _)| (ident, path))
+    }
+
+    fn builder_definition(&self, name: &Ident, config: Option<&Type>) -> TokenStream {
+        let shared: Vec<_> = self
+            .state_of_kind(StateKind::Shared)
+            .map(|(ident, path)| {
+                quote! {
+                    #ident: ::std::sync::Arc<#path>,
+                }
+            })
+            .collect();
+
+        let initializer = state_initializer(config.map(|_| quote! { &config }));
+        let shared_creator: Vec<_> = self
+            .state_of_kind(StateKind::Shared)
+            .map(|(ident, _)| {
+                quote! {
+                    let #ident = ::std::sync::Arc::new(#initializer);
+                }
+            })
+            .collect();
+        let shared_names: Vec<_> = self
+            .state_of_kind(StateKind::Shared)
+            .map(|(ident, _)| ident)
+            .collect();
+
+        match config {
+            Some(config) => quote! {
+                struct #name {
+                    config: #config,
+                    #( #shared )*
+                }
+
+                impl #name {
+                    fn new(config: #config) -> Self {
+                        #( #shared_creator )*
+                        Self { config #(, #shared_names )* }
+                    }
+                }
+            },
+            None if !shared.is_empty() => quote! {
+                struct #name {
+                    #( #shared )*
+                }
+
+                impl #name {
+                    fn new() -> Self {
+                        #( #shared_creator )*
+                        Self { #( #shared_names ),* }
+                    }
+                }
+            },
+            None => quote! {
+                struct #name;
+            },
         }
     }
 
@@ -50,10 +118,13 @@ impl LoadTestFunction {
         let state: Vec<_> = self
             .state
             .iter()
-            .map(|(ident, path)| {
-                quote! {
+            .map(|(ident, path, kind)| match kind {
+                StateKind::User => quote! {
                     #ident: #path,
-                }
+                },
+                StateKind::Shared => quote! {
+                    #ident: ::std::sync::Arc<#path>,
+                },
             })
             .collect();
 
@@ -66,14 +137,19 @@ impl LoadTestFunction {
         quote! { struct #name; }
     }
 
-    fn scenario_default(&self, name: &Ident) -> TokenStream {
+    fn scenario_default(&self, name: &Ident, config: Option<&Type>) -> TokenStream {
+        let initializer = state_initializer(config.map(|_| quote! { &self.config }));
+
         let state: Vec<_> = self
             .state
             .iter()
-            .map(|(ident, _)| {
-                quote! {
-                    #ident: Default::default(),
-                }
+            .map(|(ident, _, kind)| match kind {
+                StateKind::User => quote! {
+                    #ident: #initializer,
+                },
+                StateKind::Shared => quote! {
+                    #ident: ::std::sync::Arc::clone(&self.#ident),
+                },
             })
             .collect();
 
@@ -88,9 +164,29 @@ impl LoadTestFunction {
         }
     }
 
-    fn builder_impl(&self, name: &Ident, builder_name: &Ident) -> TokenStream {
-        let build_creator = self.scenario_default(name);
+    fn builder_impl(
+        &self,
+        name: &Ident,
+        builder_name: &Ident,
+        config: Option<&Type>,
+        hooks: &ScenarioHooks,
+    ) -> TokenStream {
+        let build_creator = self.scenario_default(name, config);
         let metric = &self.metric_type;
+        let before_all = hooks.before_all.as_ref().map(|function| {
+            quote! {
+                async fn before_all(&self) -> Result<(), MetricRecordError> {
+                    #function(self).await
+                }
+            }
+        });
+        let after_all = hooks.after_all.as_ref().map(|function| {
+            quote! {
+                async fn after_all(&self) -> Result<(), MetricRecordError> {
+                    #function(self).await
+                }
+            }
+        });
 
         quote! {
             impl profusion::prelude::ScenarioBuilder<#metric> for #builder_name {
@@ -99,41 +195,76 @@ impl LoadTestFunction {
                 fn build(&self) -> Self::Scenario {
                     #build_creator
                 }
+
+                #before_all
+                #after_all
             }
         }
     }
 
-    fn scenario_impl(&self, name: &Ident) -> TokenStream {
+    fn scenario_method(&self, method: &str, function: &Path) -> TokenStream {
         let metric = &self.metric_type;
-        let state: Vec<_> = self
+        let method = Ident::new(method, function.span());
+        let state = self.state_arguments();
+
+        quote! {
+            async fn #method(
+                &mut self,
+                aggregate: &mut MetricMeasurer< impl MetricAggregate<Metric = #metric> >
+            ) -> Result<(), MetricRecordError> {
+                #function(aggregate #(, #state )*).await
+            }
+        }
+    }
+
+    fn state_arguments(&self) -> Vec<TokenStream> {
+        self
             .state
             .iter()
-            .map(|(ident, _)| {
-                quote! {
+            .map(|(ident, _, kind)| match kind {
+                StateKind::User => quote! {
                     &mut self.#ident
-                }
+                },
+                StateKind::Shared => quote! {
+                    &self.#ident
+                },
             })
-            .collect();
+            .collect()
+    }
 
+    fn scenario_impl(&self, name: &Ident, hooks: &ScenarioHooks) -> TokenStream {
+        let metric = &self.metric_type;
         let function_name = &self.function.sig.ident;
+        let execute = self.scenario_method("execute", &Path::from(function_name.clone()));
+        let setup = hooks
+            .setup
+            .as_ref()
+            .map(|function| self.scenario_method("setup", function));
+        let teardown = hooks
+            .teardown
+            .as_ref()
+            .map(|function| self.scenario_method("teardown", function));
 
         quote! {
             impl profusion::prelude::Scenario<#metric> for #name {
-                async fn execute(
-                    &mut self,
-                    aggregate: &mut MetricMeasurer< impl MetricAggregate<Metric = #metric> >
-                ) -> Result<(), MetricRecordError> {
-                    #function_name(aggregate #(, #state )*).await
-                }
+                #setup
+                #execute
+                #teardown
             }
         }
     }
 
     pub(crate) fn generate(&self, arguments: &ScenarioArguments) -> TokenStream {
-        let builder_definition = self.builder_definition(arguments.builder_name());
-        let builder_impl = self.builder_impl(arguments.name(), arguments.builder_name());
+        let builder_definition =
+            self.builder_definition(arguments.builder_name(), arguments.config());
+        let builder_impl = self.builder_impl(
+            arguments.name(),
+            arguments.builder_name(),
+            arguments.config(),
+            arguments.hooks(),
+        );
         let scenario_definition = self.scenario_definition(arguments.name());
-        let scenario_impl = self.scenario_impl(arguments.name());
+        let scenario_impl = self.scenario_impl(arguments.name(), arguments.hooks());
         let function_definition = &self.function;
 
         quote! {
@@ -146,7 +277,16 @@ impl LoadTestFunction {
     }
 }
 
-fn process_function_input(signature: &Signature) -> Result<(Vec<(Ident, Path)>, Type), syn::Error> {
+fn state_initializer(config: Option<TokenStream>) -> TokenStream {
+    match config {
+        Some(config) => quote! { From::from(#config) },
+        None => quote! { Default::default() },
+    }
+}
+
+fn process_function_input(
+    signature: &Signature,
+) -> Result<(Vec<StateArgument>, Type), syn::Error> {
     let metric_type = match signature.inputs.first() {
         Some(FnArg::Typed(path))
             if is_of_type(&path.ty, "MetricMeasurer") && is_mutable_reference(&path.ty) =>
@@ -190,17 +330,14 @@ fn process_function_input(signature: &Signature) -> Result<(Vec<(Ident, Path)>,
     for item in signature.inputs.iter().skip(1) {
         match item {
             FnArg::Typed(PatType { ty, pat, .. }) => {
-                match (
-                    extract_type_path(ty),
-                    *(pat.clone()),
-                    is_mutable_reference(ty),
-                ) {
-                    (Some(type_name), Pat::Ident(ident), true) => {
-                        types.push((ident.ident, type_name.path.clone()))
+                match (extract_type_path(ty), *(pat.clone()), reference_kind(ty)) {
+                    (Some(type_name), Pat::Ident(ident), Some(kind)) => {
+                        types.push((ident.ident, type_name.path.clone(), kind))
                     }
                     _ => return Err(syn::Error::new(
                         item.span(),
-                        "state for load tests should be a &mut type that implements Default trait",
+                        "state for load tests should be either `&mut T` created for each user or `&T` shared between users, \
+                        where `T` implements Default trait or From<&Config>",
                     )),
                 }
             }
@@ -212,7 +349,7 @@ fn process_function_input(signature: &Signature) -> Result<(Vec<(Ident, Path)>,
 }
 
 fn is_of_type(item_type: &Type, expected: &str) -> bool {
-    extract_type_path(item_type).map_or(false, |path| is_path_of_type(&path.path, expected))
+    extract_type_path(item_type).is_some_and(|path| is_path_of_type(&path.path, expected))
 }
 
 fn is_path_of_type(path: &Path, expected: &str) -> bool {
@@ -273,6 +410,14 @@ fn is_mutable_reference(item_type: &Type) -> bool {
     matches!(item_type, Type::Reference(reference) if reference.mutability.is_some())
 }
 
+fn reference_kind(item_type: &Type) -> Option<StateKind> {
+    match item_type {
+        Type::Reference(reference) if reference.mutability.is_some() => Some(StateKind::User),
+        Type::Reference(_) => Some(StateKind::Shared),
+        _ => None,
+    }
+}
+
 fn validate_return_type(result: &ReturnType) -> Result<(), syn::Error> {
     if let ReturnType::Type(_, return_type) = result {
         if is_of_type(return_type, "Result") {
@@ -467,6 +612,39 @@ mod process_function_arguments_tests {
         assert_eq!(result[1].0, Ident::new("state_two", result[0].0.span()));
         assert_eq!(result[2].0, Ident::new("state_three", result[0].0.span()));
     }
+
+    #[test]
+    fn distinguishes_shared_and_user_state_arguments() {
+        let (result, _metric_type) = process_function_input(
+            &parse2::<Signature>(quote! {
+                async fn load_test(
+                    measurer: &mut MetricMeasurer<impl MetricAggregate<Metric=TestTwo>>,
+                    catalog: &item_crate::Catalog,
+                    state: &mut item_crate::State,
+                )
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(result[0].2, StateKind::Shared);
+        assert_eq!(result[1].2, StateKind::User);
+    }
+
+    #[test]
+    fn error_when_state_argument_is_not_a_reference_to_type() {
+        let result = process_function_input(
+            &parse2::<Signature>(quote! {
+                async fn load_test(
+                    measurer: &mut MetricMeasurer<impl MetricAggregate<Metric=TestTwo>>,
+                    catalog: &[item_crate::Catalog],
+                )
+            })
+            .unwrap(),
+        );
+
+        assert!(result.is_err());
+    }
 }
 
 #[cfg(test)]
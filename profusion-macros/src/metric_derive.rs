@@ -0,0 +1,306 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+    Attribute, Data, DeriveInput, Fields, Ident, LitStr,
+};
+
+#[derive(Debug)]
+pub(crate) struct MetricDerive {
+    input: DeriveInput,
+    variants: Vec<(Ident, String)>,
+    bounds: bool,
+    all: bool,
+}
+
+impl Parse for MetricDerive {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let input = input.parse::<DeriveInput>()?;
+
+        let variants = match &input.data {
+            Data::Enum(data) => data
+                .variants
+                .iter()
+                .map(|variant| match variant.fields {
+                    Fields::Unit => Ok((
+                        variant.ident.clone(),
+                        match metric_name(&variant.attrs)? {
+                            Some(name) => name,
+                            None => snake_case(&variant.ident.to_string()),
+                        },
+                    )),
+                    _ => Err(syn::Error::new(
+                        variant.span(),
+                        "metric variant should not contain any fields",
+                    )),
+                })
+                .collect::<syn::Result<Vec<_>>>()?,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "Metric can be derived only for enums with unit variants",
+                ))
+            }
+        };
+
+        let (mut bounds, mut all) = (false, false);
+
+        for attribute in input.attrs.iter().filter(|attr| attr.path().is_ident("metric")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("bounds") {
+                    bounds = true;
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("all") {
+                    all = true;
+                    return Ok(());
+                }
+
+                Err(meta.error("unsupported metric attribute, expected `bounds` or `all`"))
+            })?;
+        }
+
+        Ok(Self {
+            input,
+            variants,
+            bounds,
+            all,
+        })
+    }
+}
+
+impl MetricDerive {
+    fn metric_impl(&self) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, type_generics, where_clause) = self.input.generics.split_for_impl();
+        let arms = self.variants.iter().map(|(variant, metric_name)| {
+            quote! { Self::#variant => #metric_name, }
+        });
+
+        quote! {
+            impl #impl_generics profusion::prelude::Metric for #name #type_generics #where_clause {
+                fn name(&self) -> &'static str {
+                    match self {
+                        #( #arms )*
+                    }
+                }
+            }
+        }
+    }
+
+    fn bounds_impl(&self) -> TokenStream {
+        if !self.bounds {
+            return TokenStream::new();
+        }
+
+        let name = &self.input.ident;
+        let (impl_generics, type_generics, where_clause) = self.input.generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics ::core::clone::Clone for #name #type_generics #where_clause {
+                fn clone(&self) -> Self {
+                    *self
+                }
+            }
+
+            impl #impl_generics ::core::marker::Copy for #name #type_generics #where_clause {}
+
+            impl #impl_generics ::core::cmp::PartialEq for #name #type_generics #where_clause {
+                fn eq(&self, other: &Self) -> bool {
+                    ::core::mem::discriminant(self) == ::core::mem::discriminant(other)
+                }
+            }
+
+            impl #impl_generics ::core::cmp::Eq for #name #type_generics #where_clause {}
+
+            impl #impl_generics ::core::hash::Hash for #name #type_generics #where_clause {
+                fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                    ::core::hash::Hash::hash(&::core::mem::discriminant(self), state)
+                }
+            }
+        }
+    }
+
+    fn all_impl(&self) -> TokenStream {
+        if !self.all {
+            return TokenStream::new();
+        }
+
+        let name = &self.input.ident;
+        let (impl_generics, type_generics, where_clause) = self.input.generics.split_for_impl();
+        let length = self.variants.len();
+        let variants = self.variants.iter().map(|(variant, _)| quote! { Self::#variant, });
+
+        quote! {
+            impl #impl_generics #name #type_generics #where_clause {
+                /// All metrics in order of declaration
+                pub const ALL: [Self; #length] = [ #( #variants )* ];
+            }
+        }
+    }
+}
+
+impl ToTokens for MetricDerive {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.metric_impl().to_tokens(tokens);
+        self.bounds_impl().to_tokens(tokens);
+        self.all_impl().to_tokens(tokens);
+    }
+}
+
+fn metric_name(attributes: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut name = None;
+
+    for attribute in attributes.iter().filter(|attr| attr.path().is_ident("metric")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                return Ok(());
+            }
+
+            Err(meta.error("unsupported metric attribute, expected `name = \"...\"`"))
+        })?;
+    }
+
+    Ok(name)
+}
+
+fn snake_case(value: &str) -> String {
+    let characters: Vec<char> = value.chars().collect();
+    let mut result = String::with_capacity(value.len() + 4);
+
+    for (index, character) in characters.iter().enumerate() {
+        if character.is_uppercase() && index > 0 {
+            let previous = characters[index - 1];
+            let next_is_lowercase =
+                characters.get(index + 1).is_some_and(|next| next.is_lowercase());
+
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lowercase)
+            {
+                result.push('_');
+            }
+        }
+
+        result.extend(character.to_lowercase());
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::parse2;
+
+    use super::*;
+
+    #[test]
+    fn converts_variant_names_to_snake_case() {
+        assert_eq!(snake_case("ConnectionTime"), "connection_time");
+        assert_eq!(snake_case("Request"), "request");
+        assert_eq!(snake_case("HTTPRequest"), "http_request");
+        assert_eq!(snake_case("Step2Time"), "step2_time");
+    }
+
+    #[test]
+    fn returns_error_on_struct() {
+        let result = parse2::<MetricDerive>(quote! { struct TestMetric; });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn returns_error_on_variant_with_fields() {
+        let result = parse2::<MetricDerive>(quote! {
+            enum TestMetric {
+                One,
+                Two(usize),
+            }
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn returns_error_on_unknown_attribute() {
+        let result = parse2::<MetricDerive>(quote! {
+            #[metric(unknown)]
+            enum TestMetric {
+                One,
+            }
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn generates_only_metric_by_default() {
+        let derive = parse2::<MetricDerive>(quote! {
+            enum TestMetric {
+                ConnectionTime,
+                #[metric(name = "request")]
+                RequestTime,
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            derive.to_token_stream().to_string(),
+            quote! {
+                impl profusion::prelude::Metric for TestMetric {
+                    fn name(&self) -> &'static str {
+                        match self {
+                            Self::ConnectionTime => "connection_time",
+                            Self::RequestTime => "request",
+                        }
+                    }
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn generates_list_of_all_metrics() {
+        let derive = parse2::<MetricDerive>(quote! {
+            #[metric(all)]
+            enum TestMetric {
+                One,
+                Two,
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            derive.all_impl().to_string(),
+            quote! {
+                impl TestMetric {
+                    /// All metrics in order of declaration
+                    pub const ALL: [Self; 2usize] = [Self::One, Self::Two,];
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn generates_bounds_when_requested() {
+        let derive = parse2::<MetricDerive>(quote! {
+            #[metric(bounds)]
+            enum TestMetric {
+                One,
+            }
+        })
+        .unwrap();
+
+        let bounds = derive.bounds_impl().to_string();
+
+        assert!(bounds.contains(":: core :: marker :: Copy for TestMetric"));
+        assert!(bounds.contains(":: core :: hash :: Hash for TestMetric"));
+        assert!(bounds.contains(":: core :: cmp :: Eq for TestMetric"));
+    }
+}
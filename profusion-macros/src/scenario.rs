@@ -119,4 +119,211 @@ mod tests {
                 .to_string()
         );
     }
+
+    #[test]
+    fn generates_state_from_builder_config() {
+        let scenario = Scenario::new(
+            parse2(quote! { MyLoadTest, config = MyConfig }).unwrap(),
+            parse2(
+                quote! {
+                    async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str>>, state_one: &mut StateOne) -> Result<(), Error> {}},
+            )
+                .unwrap(),
+        );
+
+        assert_eq!(
+            scenario.to_token_stream().to_string(),
+            quote! {
+                struct MyLoadTest {
+                    config: MyConfig,
+                }
+
+                impl MyLoadTest {
+                    fn new(config: MyConfig) -> Self {
+                        Self { config }
+                    }
+                }
+
+                struct MyLoadTestScenario {
+                     state_one: StateOne,
+                }
+
+                impl profusion::prelude::ScenarioBuilder<&'static str> for MyLoadTest {
+                    type Scenario = MyLoadTestScenario;
+                    fn build(& self) -> Self::Scenario {
+                        MyLoadTestScenario {
+                            state_one: From::from(&self.config),
+                        }
+                    }
+                }
+
+                impl profusion::prelude::Scenario<&'static str> for MyLoadTestScenario {
+                    async fn execute(
+                        &mut self,
+                        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric=& 'static str> >
+                    ) -> Result<(), MetricRecordError> {
+                        test_function(aggregate, &mut self.state_one).await
+                    }
+                }
+
+                async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str> >, state_one: &mut StateOne)
+                    -> Result<(), Error> {
+
+                }
+            }
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn generates_shared_state_in_builder() {
+        let scenario = Scenario::new(
+            parse2(quote! { MyLoadTest }).unwrap(),
+            parse2(
+                quote! {
+                    async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str>>, catalog: &Catalog, state_one: &mut StateOne) -> Result<(), Error> {}},
+            )
+                .unwrap(),
+        );
+
+        assert_eq!(
+            scenario.to_token_stream().to_string(),
+            quote! {
+                struct MyLoadTest {
+                    catalog: ::std::sync::Arc<Catalog>,
+                }
+
+                impl MyLoadTest {
+                    fn new() -> Self {
+                        let catalog = ::std::sync::Arc::new(Default::default());
+                        Self { catalog }
+                    }
+                }
+
+                struct MyLoadTestScenario {
+                     catalog: ::std::sync::Arc<Catalog>,
+                     state_one: StateOne,
+                }
+
+                impl profusion::prelude::ScenarioBuilder<&'static str> for MyLoadTest {
+                    type Scenario = MyLoadTestScenario;
+                    fn build(& self) -> Self::Scenario {
+                        MyLoadTestScenario {
+                            catalog: ::std::sync::Arc::clone(&self.catalog),
+                            state_one: Default::default(),
+                        }
+                    }
+                }
+
+                impl profusion::prelude::Scenario<&'static str> for MyLoadTestScenario {
+                    async fn execute(
+                        &mut self,
+                        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric=& 'static str> >
+                    ) -> Result<(), MetricRecordError> {
+                        test_function(aggregate, &self.catalog, &mut self.state_one).await
+                    }
+                }
+
+                async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str> >, catalog: &Catalog, state_one: &mut StateOne)
+                    -> Result<(), Error> {
+
+                }
+            }
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn generates_shared_state_from_builder_config() {
+        let scenario = Scenario::new(
+            parse2(quote! { MyLoadTest, config = MyConfig }).unwrap(),
+            parse2(
+                quote! {
+                    async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str>>, catalog: &Catalog) -> Result<(), Error> {}},
+            )
+                .unwrap(),
+        );
+
+        let code = scenario.to_token_stream().to_string();
+
+        assert!(code.contains(
+            &quote! {
+                impl MyLoadTest {
+                    fn new(config: MyConfig) -> Self {
+                        let catalog = ::std::sync::Arc::new(From::from(&config));
+                        Self { config, catalog }
+                    }
+                }
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn generates_hooks_from_companion_functions() {
+        let scenario = Scenario::new(
+            parse2(quote! { MyLoadTest, setup = login, teardown = logout, before_all = prepare, after_all = cleanup }).unwrap(),
+            parse2(
+                quote! {
+                    async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str>>, state_one: &mut StateOne) -> Result<(), Error> {}},
+            )
+                .unwrap(),
+        );
+
+        assert_eq!(
+            scenario.to_token_stream().to_string(),
+            quote! {
+                struct MyLoadTest;
+                struct MyLoadTestScenario {
+                     state_one: StateOne,
+                }
+
+                impl profusion::prelude::ScenarioBuilder<&'static str> for MyLoadTest {
+                    type Scenario = MyLoadTestScenario;
+                    fn build(& self) -> Self::Scenario {
+                        MyLoadTestScenario {
+                            state_one: Default::default(),
+                        }
+                    }
+
+                    async fn before_all(&self) -> Result<(), MetricRecordError> {
+                        prepare(self).await
+                    }
+
+                    async fn after_all(&self) -> Result<(), MetricRecordError> {
+                        cleanup(self).await
+                    }
+                }
+
+                impl profusion::prelude::Scenario<&'static str> for MyLoadTestScenario {
+                    async fn setup(
+                        &mut self,
+                        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric=& 'static str> >
+                    ) -> Result<(), MetricRecordError> {
+                        login(aggregate, &mut self.state_one).await
+                    }
+
+                    async fn execute(
+                        &mut self,
+                        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric=& 'static str> >
+                    ) -> Result<(), MetricRecordError> {
+                        test_function(aggregate, &mut self.state_one).await
+                    }
+
+                    async fn teardown(
+                        &mut self,
+                        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric=& 'static str> >
+                    ) -> Result<(), MetricRecordError> {
+                        logout(aggregate, &mut self.state_one).await
+                    }
+                }
+
+                async fn test_function(report: &mut MetricMeasurer<impl MetricAggregate<Metric=&'static str> >, state_one: &mut StateOne)
+                    -> Result<(), Error> {
+
+                }
+            }
+                .to_string()
+        );
+    }
 }
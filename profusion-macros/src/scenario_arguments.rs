@@ -1,11 +1,22 @@
 use quote::format_ident;
-use syn::{Error, Ident, Token};
+use syn::{Error, Ident, Path, Token, Type};
 use syn::parse::{Parse, ParseStream};
 
 #[derive(Debug)]
 pub(crate) struct ScenarioArguments {
     name: Ident,
     builder_name: Ident,
+    config: Option<Type>,
+    hooks: ScenarioHooks,
+}
+
+/// Companion functions of the scenario specified as `key = function` options
+#[derive(Debug, Default)]
+pub(crate) struct ScenarioHooks {
+    pub(crate) setup: Option<Path>,
+    pub(crate) teardown: Option<Path>,
+    pub(crate) before_all: Option<Path>,
+    pub(crate) after_all: Option<Path>,
 }
 
 impl Parse for ScenarioArguments {
@@ -15,12 +26,46 @@ impl Parse for ScenarioArguments {
             .map_err(|e| Error::new(e.span(), "missing struct name for scenario"))?;
 
         let mut name = format_ident!("{builder_name}Scenario");
+        let mut config = None;
+        let mut hooks = ScenarioHooks::default();
 
-        if input.peek(Token![,]) {
+        if input.peek(Token![,]) && !input.peek3(Token![=]) {
             input.parse::<Token![,]>()?;
             name = input.parse::<Ident>()?;
         }
 
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            let key = input.parse::<Ident>()?;
+
+            if !input.peek(Token![=]) {
+                return Err(Error::new(
+                    key.span(),
+                    "There should be only two struct names followed by `key = value` options",
+                ));
+            }
+
+            input.parse::<Token![=]>()?;
+
+            let is_duplicate = match key.to_string().as_str() {
+                "config" => config.replace(input.parse::<Type>()?).is_some(),
+                "setup" => hooks.setup.replace(input.parse::<Path>()?).is_some(),
+                "teardown" => hooks.teardown.replace(input.parse::<Path>()?).is_some(),
+                "before_all" => hooks.before_all.replace(input.parse::<Path>()?).is_some(),
+                "after_all" => hooks.after_all.replace(input.parse::<Path>()?).is_some(),
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        "unsupported option, expected one of `config`, `setup`, `teardown`, `before_all` or `after_all`",
+                    ))
+                }
+            };
+
+            if is_duplicate {
+                return Err(Error::new(key.span(), format!("duplicate `{key}` option")));
+            }
+        }
+
         if !input.is_empty() {
             return Err(Error::new(
                 input.span(),
@@ -28,7 +73,12 @@ impl Parse for ScenarioArguments {
             ));
         }
 
-        Ok(Self { name, builder_name })
+        Ok(Self {
+            name,
+            builder_name,
+            config,
+            hooks,
+        })
     }
 }
 
@@ -40,6 +90,14 @@ impl ScenarioArguments {
     pub(crate) fn builder_name(&self) -> &Ident {
         &self.builder_name
     }
+
+    pub(crate) fn config(&self) -> Option<&Type> {
+        self.config.as_ref()
+    }
+
+    pub(crate) fn hooks(&self) -> &ScenarioHooks {
+        &self.hooks
+    }
 }
 
 #[cfg(test)]
@@ -67,7 +125,7 @@ mod test {
         let arguments = parse2::<ScenarioArguments>(quote! { ItemName, ItemNameBuilder, TooMuch });
 
         assert!(arguments.is_err());
-        print!("{}", arguments.unwrap_err().to_string());
+        print!("{}", arguments.unwrap_err());
     }
 
     #[test]
@@ -83,6 +141,76 @@ mod test {
         );
     }
 
+    #[test]
+    fn creates_args_with_config_type() {
+        let arguments =
+            parse2::<ScenarioArguments>(quote! { ItemName, config = crate::MyConfig }).unwrap();
+
+        assert_eq!(
+            arguments.name,
+            Ident::new("ItemNameScenario", arguments.name.span())
+        );
+        assert_eq!(
+            arguments.config().unwrap(),
+            &parse2::<Type>(quote! { crate::MyConfig }).unwrap()
+        );
+    }
+
+    #[test]
+    fn creates_args_with_custom_scenario_name_and_config_type() {
+        let arguments =
+            parse2::<ScenarioArguments>(quote! { ItemName, CustomScenario, config = MyConfig })
+                .unwrap();
+
+        assert_eq!(
+            arguments.name,
+            Ident::new("CustomScenario", arguments.name.span())
+        );
+        assert!(arguments.config().is_some());
+    }
+
+    #[test]
+    fn unknown_option_results_in_error() {
+        let arguments = parse2::<ScenarioArguments>(quote! { ItemName, unknown = MyConfig });
+
+        assert!(arguments.is_err());
+    }
+
+    #[test]
+    fn creates_args_with_hooks() {
+        let arguments = parse2::<ScenarioArguments>(quote! {
+            ItemName,
+            setup = login,
+            teardown = auth::logout,
+            before_all = prepare,
+            after_all = cleanup
+        })
+        .unwrap();
+
+        let hooks = arguments.hooks();
+
+        assert_eq!(hooks.setup, Some(parse2(quote! { login }).unwrap()));
+        assert_eq!(hooks.teardown, Some(parse2(quote! { auth::logout }).unwrap()));
+        assert_eq!(hooks.before_all, Some(parse2(quote! { prepare }).unwrap()));
+        assert_eq!(hooks.after_all, Some(parse2(quote! { cleanup }).unwrap()));
+    }
+
+    #[test]
+    fn duplicate_hook_results_in_error() {
+        let arguments =
+            parse2::<ScenarioArguments>(quote! { ItemName, setup = one, setup = two });
+
+        assert!(arguments.is_err());
+    }
+
+    #[test]
+    fn duplicate_config_results_in_error() {
+        let arguments =
+            parse2::<ScenarioArguments>(quote! { ItemName, config = One, config = Two });
+
+        assert!(arguments.is_err());
+    }
+
     #[test]
     fn creates_args_with_custom_scenario_suffix() {
         let arguments =
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use profusion::prelude::*;
+
+#[derive(Metric, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[metric(all)]
+enum ShopMetric {
+    ProductPage,
+    #[metric(name = "search")]
+    SearchResults,
+    HTTPCheckout,
+}
+
+#[derive(Metric, Debug)]
+#[metric(bounds)]
+enum GeneratedBoundsMetric {
+    One,
+}
+
+#[test]
+fn generates_snake_case_names() {
+    assert_eq!(ShopMetric::ProductPage.name(), "product_page");
+    assert_eq!(ShopMetric::HTTPCheckout.name(), "http_checkout");
+}
+
+#[test]
+fn allows_overriding_metric_name() {
+    assert_eq!(ShopMetric::SearchResults.name(), "search");
+}
+
+#[test]
+fn lists_all_metrics_in_declaration_order() {
+    assert_eq!(
+        ShopMetric::ALL.iter().map(Metric::name).collect::<Vec<_>>(),
+        vec!["product_page", "search", "http_checkout"]
+    );
+}
+
+#[test]
+fn can_be_used_as_hashmap_key() {
+    let mut map = HashMap::new();
+
+    *map.entry(ShopMetric::ProductPage).or_default() += 1;
+    *map.entry(ShopMetric::ProductPage).or_default() += 2;
+    *map.entry(ShopMetric::HTTPCheckout).or_default() += 1;
+
+    assert_eq!(
+        map,
+        HashMap::from([(ShopMetric::ProductPage, 3), (ShopMetric::HTTPCheckout, 1)])
+    );
+}
+
+#[test]
+fn generates_bounds_on_request() {
+    let metric = GeneratedBoundsMetric::One;
+    let copy = metric;
+
+    assert_eq!(metric, copy);
+    assert_eq!(copy.name(), "one");
+}
+
+#[test]
+fn works_with_timeline_aggregate() {
+    let mut aggregate = TimelineAggregateBuilder::new(MetricAggregateStorage::default()).build();
+
+    aggregate.add_entry(
+        ShopMetric::ProductPage,
+        std::time::Duration::from_micros(20),
+        None,
+    );
+
+    assert_eq!(aggregate.flush().0.count(ShopMetric::ProductPage), 1);
+}
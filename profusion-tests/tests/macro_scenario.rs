@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 use profusion::prelude::*;
@@ -16,6 +17,35 @@ impl State {
     }
 }
 
+struct Config {
+    limit: usize,
+}
+
+struct LimitedState {
+    remaining: usize,
+}
+
+impl From<&Config> for LimitedState {
+    fn from(config: &Config) -> Self {
+        Self {
+            remaining: config.limit,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Catalog {
+    visits: AtomicUsize,
+}
+
+impl From<&Config> for Catalog {
+    fn from(config: &Config) -> Self {
+        Self {
+            visits: AtomicUsize::new(config.limit),
+        }
+    }
+}
+
 #[scenario(LoadTestOne)]
 async fn load_test_items(
     reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
@@ -23,6 +53,7 @@ async fn load_test_items(
     reporter.measure("load_test_one", async { Ok(()) }).await?
 }
 
+#[allow(dead_code, clippy::unit_arg)]
 #[scenario(LoadTestTwo)]
 async fn load_test_items_with_state(
     reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
@@ -36,9 +67,177 @@ async fn load_test_items_with_state(
     reporter.measure("load_test_two", async { Ok(state_one.incr()) }).await?
 }
 
+#[scenario(LoadTestConfigured, config = Config)]
+async fn load_test_items_with_config(
+    reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
+    state: &mut LimitedState,
+) -> Result<(), MetricRecordError> {
+    if state.remaining == 0 {
+        return Err(std::io::Error::other("limit reached").into());
+    }
+
+    reporter
+        .measure("load_test_configured", async {
+            state.remaining -= 1;
+        })
+        .await
+}
+
+#[scenario(LoadTestShared)]
+async fn load_test_items_with_shared_state(
+    reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
+    catalog: &Catalog,
+    state: &mut State,
+) -> Result<(), MetricRecordError> {
+    reporter
+        .measure("load_test_shared", async {
+            catalog.visits.fetch_add(1, Ordering::Relaxed);
+            state.incr();
+        })
+        .await
+}
+
+#[scenario(LoadTestSharedConfigured, config = Config)]
+async fn load_test_items_with_shared_configured_state(
+    reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
+    catalog: &Catalog,
+) -> Result<(), MetricRecordError> {
+    reporter
+        .measure("load_test_shared_configured", async {
+            catalog.visits.fetch_add(1, Ordering::Relaxed);
+        })
+        .await
+}
+
+#[derive(Default)]
+struct Session {
+    logged_in: bool,
+    requests: usize,
+}
+
+#[scenario(LoadTestWithHooks, setup = login, teardown = logout, before_all = prepare, after_all = cleanup)]
+async fn load_test_items_with_hooks(
+    reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
+    catalog: &Catalog,
+    session: &mut Session,
+) -> Result<(), MetricRecordError> {
+    if !session.logged_in {
+        return Err(std::io::Error::other("not logged in").into());
+    }
+
+    reporter
+        .measure("request", async {
+            catalog.visits.fetch_add(1, Ordering::Relaxed);
+            session.requests += 1;
+        })
+        .await
+}
+
+async fn login(
+    reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
+    _catalog: &Catalog,
+    session: &mut Session,
+) -> Result<(), MetricRecordError> {
+    reporter
+        .measure("login", async {
+            session.logged_in = true;
+        })
+        .await
+}
+
+async fn logout(
+    _reporter: &mut MetricMeasurer<impl MetricAggregate<Metric = &'static str>>,
+    _catalog: &Catalog,
+    session: &mut Session,
+) -> Result<(), MetricRecordError> {
+    session.logged_in = false;
+    Ok(())
+}
+
+async fn prepare(builder: &LoadTestWithHooks) -> Result<(), MetricRecordError> {
+    builder.catalog.visits.store(100, Ordering::Relaxed);
+    Ok(())
+}
+
+async fn cleanup(builder: &LoadTestWithHooks) -> Result<(), MetricRecordError> {
+    builder.catalog.visits.store(0, Ordering::Relaxed);
+    Ok(())
+}
+
 #[tokio::test]
+#[allow(unused_variables)]
 async fn stateless_scenario() -> Result<(), MetricRecordError> {
     let item = LoadTestOne;
 
     Ok(())
 }
+
+#[tokio::test]
+async fn scenario_state_is_created_from_builder_config() -> Result<(), MetricRecordError> {
+    let builder = LoadTestConfigured::new(Config { limit: 2 });
+    let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+    let mut scenario = builder.build();
+
+    scenario.execute(&mut measurer).await?;
+    scenario.execute(&mut measurer).await?;
+
+    assert!(scenario.execute(&mut measurer).await.is_err());
+    assert!(builder.build().execute(&mut measurer).await.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn shared_state_is_created_once_for_all_users() -> Result<(), MetricRecordError> {
+    let builder = LoadTestShared::new();
+    let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+    let (mut one, mut two) = (builder.build(), builder.build());
+
+    one.execute(&mut measurer).await?;
+    two.execute(&mut measurer).await?;
+    two.execute(&mut measurer).await?;
+
+    assert_eq!(builder.catalog.visits.load(Ordering::Relaxed), 3);
+    assert_eq!(one.state.value(), 1);
+    assert_eq!(two.state.value(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn shared_state_is_created_from_builder_config() -> Result<(), MetricRecordError> {
+    let builder = LoadTestSharedConfigured::new(Config { limit: 10 });
+    let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+    builder.build().execute(&mut measurer).await?;
+    builder.build().execute(&mut measurer).await?;
+
+    assert_eq!(builder.catalog.visits.load(Ordering::Relaxed), 12);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn scenario_hooks_are_executed_around_iterations() -> Result<(), MetricRecordError> {
+    let builder = LoadTestWithHooks::new();
+    let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+    let mut scenario = builder.build();
+
+    builder.before_all().await?;
+
+    assert!(scenario.execute(&mut measurer).await.is_err());
+
+    scenario.setup(&mut measurer).await?;
+    scenario.execute(&mut measurer).await?;
+    scenario.teardown(&mut measurer).await?;
+
+    assert!(!scenario.session.logged_in);
+    assert_eq!(scenario.session.requests, 1);
+    assert_eq!(builder.catalog.visits.load(Ordering::Relaxed), 101);
+
+    builder.after_all().await?;
+
+    assert_eq!(builder.catalog.visits.load(Ordering::Relaxed), 0);
+
+    Ok(())
+}
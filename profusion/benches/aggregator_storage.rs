@@ -1,7 +1,37 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
 use criterion::{black_box, Criterion, criterion_group, criterion_main};
 
 use profusion::prelude::*;
 
+/// Allocator that keeps track of allocated bytes to report memory retained by aggregates
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+
+        if !pointer.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Ord, PartialOrd)]
 enum BenchMetric {
     One,
@@ -138,7 +168,253 @@ fn criterion_benchmark(c: &mut Criterion) {
         let storage = MetricAggregateStorage::default();
         bench.iter(move || populate_values_bench(storage.clone(), values.clone()));
     });
+
+    group.bench_with_input("summary::small", &small_values, |bench, values| {
+        let storage = SummaryAggregateStorage::default();
+        bench.iter(move || populate_values_bench(storage.clone(), values.clone()));
+    });
+
+    group.bench_with_input("summary::large", &large_values, |bench, values| {
+        let storage = SummaryAggregateStorage::default();
+        bench.iter(move || populate_values_bench(storage.clone(), values.clone()));
+    });
+}
+
+const VIRTUAL_USERS: usize = 100;
+
+const THREADS: usize = 4;
+
+fn populate_aggregate_bench<A>(aggregate: &mut A, values: &[(BenchMetric, u64)])
+where
+    A: MetricAggregate<Metric = BenchMetric>,
+{
+    for (metric, value) in values.iter() {
+        aggregate.add_entry(*metric, Duration::from_micros(*value), None)
+    }
+}
+
+/// Populates users spread across threads, returns their aggregates left for the final merge
+fn per_user_threads<S>(
+    builder: &TimelineAggregateBuilder<S>,
+    values: &[(BenchMetric, u64)],
+) -> Vec<TimelineAggregate<S>>
+where
+    S: AggregateStorage<Metric = BenchMetric> + Send + Sync,
+{
+    thread::scope(|scope| {
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut users: Vec<_> =
+                        (0..VIRTUAL_USERS / THREADS).map(|_| builder.build()).collect();
+
+                    for user in users.iter_mut() {
+                        populate_aggregate_bench(user, values);
+                    }
+
+                    users
+                })
+            })
+            .collect();
+
+        threads.into_iter().flat_map(|thread| thread.join().unwrap()).collect()
+    })
+}
+
+/// Populates users spread across threads, which hand over their windows into shards
+fn sharded_threads<S>(builder: &ShardedTimelineAggregateBuilder<S>, values: &[(BenchMetric, u64)])
+where
+    S: AggregateStorage<Metric = BenchMetric> + Send + Sync,
+{
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                let mut users: Vec<_> =
+                    (0..VIRTUAL_USERS / THREADS).map(|_| builder.build()).collect();
+
+                for user in users.iter_mut() {
+                    populate_aggregate_bench(user, values);
+                }
+            });
+        }
+    });
+}
+
+/// Prints memory retained by aggregates of all users before they are merged into one timeline
+fn report_retained<R>(name: &str, populate: impl FnOnce() -> R) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let retained = populate();
+
+    println!(
+        "{name}: {} KiB retained before final merge",
+        ALLOCATED.load(Ordering::Relaxed).saturating_sub(before) / 1024
+    );
+
+    drop(retained);
+}
+
+fn aggregate_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aggregate_design");
+    let values = black_box(
+        (0..=200usize)
+            .map(|index| {
+                (
+                    match index % 3 {
+                        0 => BenchMetric::One,
+                        1 => BenchMetric::Two,
+                        _ => BenchMetric::Three,
+                    },
+                    (index % 100 * 53) as u64,
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    let settings = AggregateSettings::default().with_window(Duration::from_micros(20));
+
+    group.bench_with_input("timeline::per_user", &values, |bench, values| {
+        bench.iter(|| {
+            let builder =
+                TimelineAggregateBuilder::with_settings(MetricAggregateStorage::default(), settings);
+            let mut collector = builder.build();
+            let mut users: Vec<_> = (0..VIRTUAL_USERS).map(|_| builder.build()).collect();
+
+            for user in users.iter_mut() {
+                populate_aggregate_bench(user, values);
+            }
+
+            for user in users.into_iter() {
+                user.merge_into(&mut collector);
+            }
+
+            collector.flush()
+        });
+    });
+
+    group.bench_with_input("timeline::per_user_summary", &values, |bench, values| {
+        bench.iter(|| {
+            let builder = TimelineAggregateBuilder::with_settings(
+                SummaryAggregateStorage::default(),
+                settings,
+            );
+            let mut collector = builder.build();
+            let mut users: Vec<_> = (0..VIRTUAL_USERS).map(|_| builder.build()).collect();
+
+            for user in users.iter_mut() {
+                populate_aggregate_bench(user, values);
+            }
+
+            for user in users.into_iter() {
+                user.merge_into(&mut collector);
+            }
+
+            collector.flush()
+        });
+    });
+
+    group.bench_with_input("timeline::sharded", &values, |bench, values| {
+        bench.iter(|| {
+            let builder = ShardedTimelineAggregateBuilder::with_settings(
+                MetricAggregateStorage::default(),
+                settings,
+                4,
+            );
+            let mut users: Vec<_> = (0..VIRTUAL_USERS).map(|_| builder.build()).collect();
+
+            for user in users.iter_mut() {
+                populate_aggregate_bench(user, values);
+            }
+
+            drop(users);
+
+            builder.flush().flush()
+        });
+    });
+
+    let builder =
+        TimelineAggregateBuilder::with_settings(MetricAggregateStorage::default(), settings);
+    report_retained("timeline::per_user_threads", || {
+        per_user_threads(&builder, &values)
+    });
+
+    group.bench_with_input("timeline::per_user_threads", &values, |bench, values| {
+        bench.iter(|| {
+            let builder =
+                TimelineAggregateBuilder::with_settings(MetricAggregateStorage::default(), settings);
+            let mut collector = builder.build();
+
+            for user in per_user_threads(&builder, values) {
+                user.merge_into(&mut collector);
+            }
+
+            collector.flush()
+        });
+    });
+
+    let builder = ShardedTimelineAggregateBuilder::with_settings(
+        MetricAggregateStorage::default(),
+        settings,
+        THREADS,
+    );
+    report_retained("timeline::sharded_threads", || {
+        sharded_threads(&builder, &values);
+        builder
+    });
+
+    group.bench_with_input("timeline::sharded_threads", &values, |bench, values| {
+        bench.iter(|| {
+            let builder = ShardedTimelineAggregateBuilder::with_settings(
+                MetricAggregateStorage::default(),
+                settings,
+                THREADS,
+            );
+
+            sharded_threads(&builder, values);
+
+            builder.flush().flush()
+        });
+    });
+}
+
+const OPERATIONS: usize = 10_000;
+
+/// Measures operations of the same metric from measurers running on separate threads
+fn measure_threads(settings: AggregateSettings) {
+    let builder =
+        TimelineAggregateBuilder::with_settings(MetricAggregateStorage::default(), settings);
+
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                let runtime =
+                    tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+                let mut measurer = MetricMeasurer::new(builder.build());
+
+                runtime.block_on(async {
+                    for _ in 0..OPERATIONS {
+                        measurer.measure(BenchMetric::One, async {}).await.unwrap();
+                    }
+                });
+            });
+        }
+    });
+}
+
+fn in_flight_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("in_flight");
+
+    group.bench_function("measure_threads::untracked", |bench| {
+        bench.iter(|| measure_threads(AggregateSettings::default()));
+    });
+
+    group.bench_function("measure_threads::tracked", |bench| {
+        bench.iter(|| measure_threads(AggregateSettings::default().with_in_flight(true)));
+    });
 }
 
-criterion_group!(metric_benches, criterion_benchmark);
+criterion_group!(
+    metric_benches,
+    criterion_benchmark,
+    aggregate_benchmark,
+    in_flight_benchmark
+);
 criterion_main!(metric_benches);
@@ -1,13 +1,13 @@
 use std::time::Duration;
 
 pub use scale::AggregateScale;
-pub use settings::AggregateSettings;
+pub use settings::{AggregateSettings, Downsample, EmptyWindows, RetentionError, TimelineRetention};
 pub use storage::*;
 #[cfg(any(feature = "test_util", test))]
 pub use test_aggregate::*;
 pub use timeline::*;
 
-use crate::metric::{Metric, MetricRecordError};
+use crate::metric::{Metric, MetricRecordError, MetricValue};
 pub use crate::start_time::StartTime;
 
 mod scale;
@@ -33,5 +33,16 @@ pub trait MetricAggregate {
         error: Option<&MetricRecordError>,
     );
 
+    /// Records non-latency value of a metric, e.g. number of received bytes
+    fn add_value(&mut self, _metric: Self::Metric, _value: MetricValue) {}
+
+    /// Records that measurements of `child` metric are part of `parent` metric transaction
+    fn add_relation(&mut self, _child: &str, _parent: &str) {}
+
+    /// Tracker of in-flight operations shared with other aggregates, if enabled
+    fn in_flight(&self) -> Option<&InFlightTracker> {
+        None
+    }
+
     fn merge_into(self, other: &mut Self);
 }
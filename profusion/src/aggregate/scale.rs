@@ -10,6 +10,11 @@ pub enum AggregateScale {
     Microseconds,
     Milliseconds,
     Seconds,
+    /// Records values with nanosecond precision, while reports pick the most readable unit
+    /// of each metric via [`TimelineItem::readable_scale`](crate::aggregate::TimelineItem::readable_scale)
+    ///
+    /// Aggregate builders switch histograms with a limit to [`OverflowPolicy::Resize`](crate::aggregate::OverflowPolicy::Resize)
+    Auto,
 }
 
 const NANOS_PER_SEC: u64 = 1_000_000_000;
@@ -22,7 +27,7 @@ impl AggregateScale {
     /// It is used by report aggregator to calculate stats based on chosen scale
     pub fn duration_to_value(&self, duration: Duration) -> u64 {
         match self {
-            Self::Nanoseconds => {
+            Self::Nanoseconds | Self::Auto => {
                 duration.as_secs() * NANOS_PER_SEC + duration.subsec_nanos() as u64
             }
             Self::Microseconds => {
@@ -40,7 +45,7 @@ impl AggregateScale {
     /// Can be used to generate duration based on storage report
     pub fn value_to_duration(&self, value: u64) -> Duration {
         match self {
-            AggregateScale::Nanoseconds => Duration::from_nanos(value),
+            AggregateScale::Nanoseconds | AggregateScale::Auto => Duration::from_nanos(value),
             AggregateScale::Microseconds => Duration::from_micros(value),
             AggregateScale::Milliseconds => Duration::from_millis(value),
             AggregateScale::Seconds => Duration::from_secs(value),
@@ -52,7 +57,7 @@ impl AggregateScale {
     /// Can be used to generate duration based on storage report
     pub fn aggregate_to_duration(&self, value: f64) -> Duration {
         let (seconds, nanos) = match self {
-            AggregateScale::Nanoseconds => (
+            AggregateScale::Nanoseconds | AggregateScale::Auto => (
                 value as u64 / NANOS_PER_SEC,
                 (value as u64 % NANOS_PER_SEC) as u32,
             ),
@@ -72,6 +77,58 @@ impl AggregateScale {
 
         Duration::new(seconds, nanos)
     }
+
+    /// Largest unit in which duration is at least one, e.g. milliseconds for `1.23ms`
+    pub fn readable(duration: Duration) -> Self {
+        match duration {
+            duration if duration >= Duration::from_secs(1) => Self::Seconds,
+            duration if duration >= Duration::from_millis(1) => Self::Milliseconds,
+            duration if duration >= Duration::from_micros(1) => Self::Microseconds,
+            _ => Self::Nanoseconds,
+        }
+    }
+
+    /// Unit suffix used in formatted values
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Nanoseconds | Self::Auto => "ns",
+            Self::Microseconds => "µs",
+            Self::Milliseconds => "ms",
+            Self::Seconds => "s",
+        }
+    }
+
+    /// Formats duration in the unit of the scale with up to two decimals, e.g. `4.5s`
+    ///
+    /// Automatic scale formats each duration in its [readable](Self::readable) unit,
+    /// fixed scales allow keeping the same unit for all values of a metric
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let scale = match self {
+            Self::Auto => Self::readable(duration),
+            scale => *scale,
+        };
+
+        let value = duration.as_nanos() as f64
+            / match scale {
+                Self::Seconds => NANOS_PER_SEC,
+                Self::Milliseconds => NANOS_PER_SEC / MILLIS_PER_SEC,
+                Self::Microseconds => NANOS_PER_SEC / MICROS_PER_SEC,
+                Self::Nanoseconds | Self::Auto => 1,
+            } as f64;
+
+        let formatted = format!("{value:.2}");
+        let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+
+        format!("{formatted}{}", scale.unit())
+    }
+
+    /// Formats aggregated value of latency in provided unit, e.g. `1.23ms`
+    ///
+    /// Pass [readable scale](crate::aggregate::TimelineItem::readable_scale) of a metric
+    /// to format all of its values in the same unit
+    pub fn format_value(&self, value: f64, unit: AggregateScale) -> String {
+        unit.format_duration(self.aggregate_to_duration(value))
+    }
 }
 
 #[cfg(test)]
@@ -132,6 +189,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn records_auto_scale_with_nanosecond_precision() {
+        assert_eq!(
+            AggregateScale::Auto.duration_to_value(Duration::new(1, 230)),
+            1_000_000_230
+        );
+        assert_eq!(
+            AggregateScale::Auto.value_to_duration(1_230),
+            Duration::from_nanos(1_230)
+        );
+        assert_eq!(
+            AggregateScale::Auto.aggregate_to_duration(1_230.4),
+            Duration::from_nanos(1_230)
+        );
+    }
+
+    #[test]
+    fn picks_readable_unit_for_duration() {
+        assert_eq!(
+            AggregateScale::readable(Duration::from_nanos(999)),
+            AggregateScale::Nanoseconds
+        );
+        assert_eq!(
+            AggregateScale::readable(Duration::from_micros(12)),
+            AggregateScale::Microseconds
+        );
+        assert_eq!(
+            AggregateScale::readable(Duration::from_micros(1_230)),
+            AggregateScale::Milliseconds
+        );
+        assert_eq!(
+            AggregateScale::readable(Duration::from_millis(4_500)),
+            AggregateScale::Seconds
+        );
+    }
+
+    #[test]
+    fn formats_duration_in_unit_of_scale() {
+        assert_eq!(
+            AggregateScale::Auto.format_duration(Duration::from_micros(1_234)),
+            "1.23ms"
+        );
+        assert_eq!(
+            AggregateScale::Auto.format_duration(Duration::from_millis(4_500)),
+            "4.5s"
+        );
+        assert_eq!(
+            AggregateScale::Auto.format_duration(Duration::from_nanos(250)),
+            "250ns"
+        );
+        assert_eq!(
+            AggregateScale::Milliseconds.format_duration(Duration::from_millis(4_500)),
+            "4500ms"
+        );
+        assert_eq!(
+            AggregateScale::Microseconds.format_duration(Duration::from_nanos(12_300)),
+            "12.3µs"
+        );
+    }
+
+    #[test]
+    fn formats_aggregated_value_in_provided_unit() {
+        assert_eq!(
+            AggregateScale::Microseconds.format_value(1_234.0, AggregateScale::Milliseconds),
+            "1.23ms"
+        );
+        assert_eq!(
+            AggregateScale::Microseconds.format_value(250.0, AggregateScale::Milliseconds),
+            "0.25ms"
+        );
+        assert_eq!(
+            AggregateScale::Milliseconds.format_value(4_500.0, AggregateScale::Seconds),
+            "4.5s"
+        );
+        assert_eq!(
+            AggregateScale::Auto.format_value(2_000.0, AggregateScale::Auto),
+            "2µs"
+        );
+    }
+
     #[test]
     fn converts_aggregate_to_duration() {
         assert_eq!(
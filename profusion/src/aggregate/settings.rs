@@ -1,14 +1,94 @@
 use std::time::Duration;
 
+use thiserror::Error;
+
 use crate::start_time::StartTime;
 
 use super::AggregateScale;
 
+/// Strategy for time windows without any recorded entries
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EmptyWindows {
+    /// Windows without entries are missing from the timeline
+    #[default]
+    Skip,
+    /// Windows between the first and the last entry are present in the timeline
+    BetweenEntries,
+    /// Windows from the zero point till the flush of aggregate are present in the timeline
+    WholeRun,
+}
+
+/// Retention policy for timeline windows
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TimelineRetention {
+    /// Every window is kept at full resolution
+    #[default]
+    Unbounded,
+    /// Keeps latest windows at full resolution and merges older ones into coarser buckets
+    ///
+    /// Buckets cascade into levels, each one `factor` times larger than the previous one,
+    /// so number of timeline items grows only logarithmically with the length of the run.
+    /// Created via [`TimelineRetention::downsample`].
+    Downsample(Downsample),
+}
+
+/// Validated parameters of [`TimelineRetention::Downsample`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Downsample {
+    full_resolution: usize,
+    factor: u32,
+}
+
+/// Parameters of downsampling that would keep the timeline unbounded
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RetentionError {
+    #[error("downsampling requires at least one window at full resolution")]
+    NoFullResolution,
+
+    #[error("downsampling factor has to be at least 2, got {0}")]
+    FactorTooSmall(u32),
+}
+
+impl TimelineRetention {
+    /// Downsampling retention
+    ///
+    /// # Arguments
+    ///
+    /// * `full_resolution`: number of latest windows kept at full resolution and of buckets
+    ///   kept on each level, at least 1
+    /// * `factor`: number of windows or buckets merged into a bucket of the next level, at least 2
+    pub fn downsample(full_resolution: usize, factor: u32) -> Result<Self, RetentionError> {
+        match (full_resolution, factor) {
+            (0, _) => Err(RetentionError::NoFullResolution),
+            (_, 0 | 1) => Err(RetentionError::FactorTooSmall(factor)),
+            _ => Ok(Self::Downsample(Downsample {
+                full_resolution,
+                factor,
+            })),
+        }
+    }
+}
+
+impl Downsample {
+    /// Number of latest windows kept at full resolution and of buckets kept on each level
+    pub fn full_resolution(&self) -> usize {
+        self.full_resolution
+    }
+
+    /// Number of windows or buckets merged into a bucket of the next level
+    pub fn factor(&self) -> u32 {
+        self.factor
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct AggregateSettings {
     window: Duration,
     scale: AggregateScale,
     zero: StartTime,
+    empty_windows: EmptyWindows,
+    retention: TimelineRetention,
+    in_flight: bool,
 }
 
 impl AggregateSettings {
@@ -34,6 +114,37 @@ impl AggregateSettings {
         Self { scale, ..self }
     }
 
+    /// Modifies how windows without any entries are represented in the timeline
+    ///
+    /// # Arguments
+    ///
+    /// * `empty_windows`: [`EmptyWindows`] strategy to apply on recording, merging and flushing
+    pub fn with_empty_windows(self, empty_windows: EmptyWindows) -> Self {
+        Self {
+            empty_windows,
+            ..self
+        }
+    }
+
+    /// Modifies retention policy of the timeline
+    ///
+    /// # Arguments
+    ///
+    /// * `retention`: [`TimelineRetention`] policy to apply when new windows are added
+    pub fn with_retention(self, retention: TimelineRetention) -> Self {
+        Self { retention, ..self }
+    }
+
+    /// Tracks in-flight operations of measurers for concurrency of each metric
+    ///
+    /// # Arguments
+    ///
+    /// * `in_flight`: whether aggregates provide [`InFlightTracker`](crate::aggregate::InFlightTracker)
+    ///   shared between all aggregates of a builder
+    pub fn with_in_flight(self, in_flight: bool) -> Self {
+        Self { in_flight, ..self }
+    }
+
     /// Returns current zero point
     pub fn zero(&self) -> &StartTime {
         &self.zero
@@ -48,6 +159,21 @@ impl AggregateSettings {
     pub fn scale(&self) -> AggregateScale {
         self.scale
     }
+
+    /// Returns strategy for windows without entries
+    pub fn empty_windows(&self) -> EmptyWindows {
+        self.empty_windows
+    }
+
+    /// Returns retention policy of the timeline
+    pub fn retention(&self) -> TimelineRetention {
+        self.retention
+    }
+
+    /// Returns whether in-flight operations are tracked
+    pub fn in_flight(&self) -> bool {
+        self.in_flight
+    }
 }
 
 impl Default for AggregateSettings {
@@ -56,6 +182,9 @@ impl Default for AggregateSettings {
             window: Duration::from_millis(100),
             scale: AggregateScale::default(),
             zero: StartTime::now(),
+            empty_windows: EmptyWindows::default(),
+            retention: TimelineRetention::default(),
+            in_flight: false,
         }
     }
 }
@@ -73,6 +202,53 @@ mod tests {
         assert_eq!(settings.window, Duration::from_millis(100));
     }
 
+    #[test]
+    fn skips_empty_windows_by_default() {
+        let settings = AggregateSettings::default();
+        assert_eq!(settings.empty_windows(), EmptyWindows::Skip);
+    }
+
+    #[test]
+    fn allows_modifying_empty_windows_strategy() {
+        let settings = AggregateSettings::default().with_empty_windows(EmptyWindows::WholeRun);
+        assert_eq!(settings.empty_windows(), EmptyWindows::WholeRun);
+    }
+
+    #[test]
+    fn allows_modifying_retention_policy() {
+        let retention = TimelineRetention::downsample(600, 10).unwrap();
+        let settings = AggregateSettings::default().with_retention(retention);
+
+        assert_eq!(settings.retention(), retention);
+        assert!(matches!(
+            settings.retention(),
+            TimelineRetention::Downsample(downsample)
+                if downsample.full_resolution() == 600 && downsample.factor() == 10
+        ));
+    }
+
+    #[test]
+    fn rejects_downsampling_that_keeps_timeline_unbounded() {
+        assert_eq!(
+            TimelineRetention::downsample(0, 10),
+            Err(RetentionError::NoFullResolution)
+        );
+        assert_eq!(
+            TimelineRetention::downsample(600, 1),
+            Err(RetentionError::FactorTooSmall(1))
+        );
+        assert_eq!(
+            TimelineRetention::downsample(600, 0),
+            Err(RetentionError::FactorTooSmall(0))
+        );
+    }
+
+    #[test]
+    fn tracks_in_flight_operations_on_request() {
+        assert!(!AggregateSettings::default().in_flight());
+        assert!(AggregateSettings::default().with_in_flight(true).in_flight());
+    }
+
     #[test]
     fn allows_modifying_scale() {
         let settings =
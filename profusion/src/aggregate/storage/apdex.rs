@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use rustc_hash::FxHashMap;
+
+use crate::aggregate::{AggregateScale, AggregateStorage};
+use crate::metric::Metric;
+
+/// Latency thresholds for Apdex score
+///
+/// Latency up to `satisfied` is satisfying, up to `tolerating` is tolerable
+/// and everything above it is frustrating
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApdexThresholds {
+    satisfied: Duration,
+    tolerating: Duration,
+}
+
+impl ApdexThresholds {
+    pub fn new(satisfied: Duration, tolerating: Duration) -> Self {
+        Self {
+            satisfied,
+            tolerating: tolerating.max(satisfied),
+        }
+    }
+
+    pub fn satisfied(&self) -> Duration {
+        self.satisfied
+    }
+
+    pub fn tolerating(&self) -> Duration {
+        self.tolerating
+    }
+
+    /// Thresholds converted into recorded values of the scale
+    fn to_values(self, scale: AggregateScale) -> (u64, u64) {
+        (
+            scale.duration_to_value(self.satisfied),
+            scale.duration_to_value(self.tolerating),
+        )
+    }
+}
+
+impl Default for ApdexThresholds {
+    /// Satisfied within 500ms and tolerating within 2s
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(2))
+    }
+}
+
+/// Number of satisfied, tolerating and frustrated operations of a metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ApdexCounts {
+    satisfied: u64,
+    tolerating: u64,
+    frustrated: u64,
+}
+
+impl ApdexCounts {
+    pub fn satisfied(&self) -> u64 {
+        self.satisfied
+    }
+
+    pub fn tolerating(&self) -> u64 {
+        self.tolerating
+    }
+
+    pub fn frustrated(&self) -> u64 {
+        self.frustrated
+    }
+
+    pub fn total(&self) -> u64 {
+        self.satisfied + self.tolerating + self.frustrated
+    }
+
+    /// Apdex score from 0.0 to 1.0, empty when no operations were recorded
+    pub fn score(&self) -> Option<f64> {
+        match self.total() {
+            0 => None,
+            total => Some((self.satisfied as f64 + self.tolerating as f64 / 2.0) / total as f64),
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.satisfied += other.satisfied;
+        self.tolerating += other.tolerating;
+        self.frustrated += other.frustrated;
+    }
+}
+
+/// Storage of Apdex counts per metric
+///
+/// Can be combined with other storages via [`AggregateStorage::and`]
+#[derive(Debug)]
+pub struct ApdexAggregateStorage<T> {
+    thresholds: ApdexThresholds,
+    satisfied: u64,
+    tolerating: u64,
+    inner: FxHashMap<T, ApdexCounts>,
+}
+
+impl<T> Default for ApdexAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    fn default() -> Self {
+        Self::new(ApdexThresholds::default())
+    }
+}
+
+impl<T> ApdexAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    /// Storage with thresholds compared against latencies recorded in the scale
+    /// applied via [`AggregateStorage::with_scale`], microseconds by default
+    pub fn new(thresholds: ApdexThresholds) -> Self {
+        let (satisfied, tolerating) = thresholds.to_values(AggregateScale::default());
+
+        Self {
+            thresholds,
+            satisfied,
+            tolerating,
+            inner: FxHashMap::default(),
+        }
+    }
+
+    pub fn thresholds(&self) -> ApdexThresholds {
+        self.thresholds
+    }
+
+    pub fn value(&self, metric: T) -> ApdexCounts {
+        self.inner.get(&metric).copied().unwrap_or_default()
+    }
+}
+
+impl<T> AggregateStorage for ApdexAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    type Metric = T;
+
+    #[inline]
+    fn record(&mut self, metric: Self::Metric, latency_value: u64) {
+        let counts = self.inner.entry(metric).or_default();
+
+        match latency_value {
+            value if value <= self.satisfied => counts.satisfied += 1,
+            value if value <= self.tolerating => counts.tolerating += 1,
+            _ => counts.frustrated += 1,
+        }
+    }
+
+    /// Failed operations frustrate users regardless of their latency
+    #[inline]
+    fn record_failure(&mut self, metric: Self::Metric, _latency_value: u64) {
+        self.inner.entry(metric).or_default().frustrated += 1;
+    }
+
+    fn merge(self, other: Self) -> Self {
+        let mut inner = self.inner;
+        for (metric, counts) in other.inner.into_iter() {
+            inner.entry(metric).or_default().merge(counts);
+        }
+
+        Self { inner, ..self }
+    }
+
+    fn with_scale(self, scale: AggregateScale) -> Self {
+        let (satisfied, tolerating) = self.thresholds.to_values(scale);
+
+        Self {
+            satisfied,
+            tolerating,
+            ..self
+        }
+    }
+}
+
+impl<T> Clone for ApdexAggregateStorage<T>
+where
+    T: Metric,
+{
+    fn clone(&self) -> Self {
+        Self {
+            thresholds: self.thresholds,
+            satisfied: self.satisfied,
+            tolerating: self.tolerating,
+            inner: FxHashMap::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metric::MetricValue;
+
+    use super::*;
+
+    fn thresholds() -> ApdexThresholds {
+        ApdexThresholds::new(Duration::from_micros(100), Duration::from_micros(400))
+    }
+
+    fn storage() -> ApdexAggregateStorage<&'static str> {
+        ApdexAggregateStorage::new(thresholds())
+    }
+
+    #[test]
+    fn counts_operations_by_thresholds() {
+        let mut storage = storage();
+        storage.record("one", 50);
+        storage.record("one", 100);
+        storage.record("one", 101);
+        storage.record("one", 400);
+        storage.record("one", 401);
+        storage.record("two", 1000);
+
+        assert_eq!(storage.value("one").satisfied(), 2);
+        assert_eq!(storage.value("one").tolerating(), 2);
+        assert_eq!(storage.value("one").frustrated(), 1);
+        assert_eq!(storage.value("two").frustrated(), 1);
+    }
+
+    #[test]
+    fn calculates_apdex_score() {
+        let mut storage = storage();
+        storage.record("one", 50);
+        storage.record("one", 50);
+        storage.record("one", 200);
+        storage.record("one", 1000);
+
+        assert_eq!(storage.value("one").score(), Some(0.625));
+        assert_eq!(storage.value("two").score(), None);
+    }
+
+    #[test]
+    fn ignores_non_latency_values() {
+        let mut storage = storage();
+        storage.record_value("one", MetricValue::Counter(10));
+
+        assert_eq!(storage.value("one").total(), 0);
+    }
+
+    #[test]
+    fn merges_counts_of_storages() {
+        let (mut one, mut two) = (storage(), storage());
+        one.record("one", 50);
+        two.record("one", 1000);
+        two.record("two", 200);
+
+        let merged = one.merge(two);
+
+        assert_eq!(merged.value("one").total(), 2);
+        assert_eq!(merged.value("one").score(), Some(0.5));
+        assert_eq!(merged.value("two").tolerating(), 1);
+    }
+
+    #[test]
+    fn keeps_thresholds_on_clone() {
+        let mut storage = storage();
+        storage.record("one", 50);
+
+        let clone = storage.clone();
+
+        assert_eq!(clone.thresholds(), thresholds());
+        assert_eq!(clone.value("one").total(), 0);
+    }
+
+    #[test]
+    fn counts_failed_operations_as_frustrated() {
+        let mut storage = storage();
+        storage.record_failure("one", 50);
+        storage.record("one", 50);
+
+        assert_eq!(storage.value("one").frustrated(), 1);
+        assert_eq!(storage.value("one").satisfied(), 1);
+    }
+
+    #[test]
+    fn converts_thresholds_into_scale() {
+        let mut storage = storage().with_scale(AggregateScale::Nanoseconds);
+        storage.record("one", 50_000);
+        storage.record("one", 300_000);
+        storage.record("one", 500_000);
+
+        let mut clone = storage.clone().with_scale(AggregateScale::Milliseconds);
+        clone.record("one", 1);
+
+        assert_eq!(storage.value("one").satisfied(), 1);
+        assert_eq!(storage.value("one").tolerating(), 1);
+        assert_eq!(storage.value("one").frustrated(), 1);
+        assert_eq!(clone.value("one").frustrated(), 1);
+        assert_eq!(
+            ApdexThresholds::default().tolerating(),
+            Duration::from_secs(2)
+        );
+    }
+}
@@ -1,5 +1,10 @@
-use crate::aggregate::AggregateStorage;
+use crate::aggregate::{AggregateScale, AggregateStorage};
+use crate::metric::MetricValue;
 
+/// Storage that records values into two storages at once
+///
+/// Created via [`AggregateStorage::and`], which can be chained
+/// to combine more than two storages.
 pub struct CombinedAggregateStorage<L, R>(L, R);
 
 impl<L, R> Clone for CombinedAggregateStorage<L, R>
@@ -24,6 +29,14 @@ where
     pub fn unwrap(self) -> (L, R) {
         (self.0, self.1)
     }
+
+    pub fn left(&self) -> &L {
+        &self.0
+    }
+
+    pub fn right(&self) -> &R {
+        &self.1
+    }
 }
 
 impl<L, R> Default for CombinedAggregateStorage<L, R>
@@ -45,13 +58,29 @@ where
 
     #[inline]
     fn record(&mut self, metric: Self::Metric, latency_value: u64) {
-        self.0.record(metric.clone(), latency_value);
-        self.1.record(metric.clone(), latency_value)
+        self.0.record(metric, latency_value);
+        self.1.record(metric, latency_value)
+    }
+
+    #[inline]
+    fn record_failure(&mut self, metric: Self::Metric, latency_value: u64) {
+        self.0.record_failure(metric, latency_value);
+        self.1.record_failure(metric, latency_value)
+    }
+
+    #[inline]
+    fn record_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        self.0.record_value(metric, value);
+        self.1.record_value(metric, value)
     }
 
     fn merge(self, other: Self) -> Self {
         Self(self.0.merge(other.0), self.1.merge(other.1))
     }
+
+    fn with_scale(self, scale: AggregateScale) -> Self {
+        Self(self.0.with_scale(scale), self.1.with_scale(scale))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,80 @@
+use std::cmp::{max, min};
+
+/// Aggregated samples of a gauge within a time window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GaugeValue {
+    min: u64,
+    max: u64,
+    sum: u128,
+    count: u64,
+}
+
+impl GaugeValue {
+    pub(crate) fn new(value: u64) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: value as u128,
+            count: 1,
+        }
+    }
+
+    pub(crate) fn record(&mut self, value: u64) {
+        self.merge(Self::new(value))
+    }
+
+    pub(crate) fn merge(&mut self, other: Self) {
+        self.min = min(self.min, other.min);
+        self.max = max(self.max, other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.count as f64
+    }
+
+    /// Number of recorded samples
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_recorded_samples() {
+        let mut gauge = GaugeValue::new(10);
+
+        gauge.record(30);
+        gauge.record(5);
+
+        assert_eq!(gauge.min(), 5);
+        assert_eq!(gauge.max(), 30);
+        assert_eq!(gauge.mean(), 15.0);
+        assert_eq!(gauge.count(), 3);
+    }
+
+    #[test]
+    fn merges_samples_of_other_gauge() {
+        let mut gauge = GaugeValue::new(10);
+        let mut other = GaugeValue::new(50);
+        other.record(20);
+
+        gauge.merge(other);
+
+        assert_eq!(gauge.min(), 10);
+        assert_eq!(gauge.max(), 50);
+        assert_eq!(gauge.count(), 3);
+    }
+}
@@ -2,13 +2,17 @@ use std::fmt::{Debug, Formatter};
 
 use hdrhistogram::{CreationError, Histogram};
 pub use rustc_hash::FxHashMap;
-use tracing::error;
-
-use crate::aggregate::AggregateStorage;
-use crate::metric::Metric;
+use crate::aggregate::{AggregateScale, AggregateStorage, GaugeValue, OverflowPolicy};
+use crate::metric::{Metric, MetricValue};
 
 pub struct MetricAggregateStorage<T> {
     inner: FxHashMap<T, Histogram<u64>>,
+    distributions: FxHashMap<T, Histogram<u64>>,
+    sums: FxHashMap<T, u128>,
+    counters: FxHashMap<T, u64>,
+    gauges: FxHashMap<T, GaugeValue>,
+    overflows: FxHashMap<T, u64>,
+    overflow_policy: OverflowPolicy,
     proto: Histogram<u64>,
 }
 
@@ -42,24 +46,143 @@ where
     pub fn with_limit(sigfig: u8, max_value: u64) -> Result<Self, CreationError> {
         let histogram = Histogram::new_with_max(max_value, sigfig)?;
 
-        Ok(Self {
-            proto: histogram,
-            inner: FxHashMap::default(),
-        })
+        Ok(Self::with_proto(histogram))
     }
 
     pub fn with_sigfig(sigfig: u8) -> Result<Self, CreationError> {
         let histogram = Histogram::new(sigfig)?;
 
-        Ok(Self {
-            proto: histogram,
+        Ok(Self::with_proto(histogram))
+    }
+
+    fn with_proto(proto: Histogram<u64>) -> Self {
+        Self {
+            proto,
             inner: FxHashMap::default(),
-        })
+            distributions: FxHashMap::default(),
+            sums: FxHashMap::default(),
+            counters: FxHashMap::default(),
+            gauges: FxHashMap::default(),
+            overflows: FxHashMap::default(),
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+
+    /// Changes handling of values above the limit set via [`Self::with_limit`]
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        policy.apply(&mut self.proto);
+        self.overflow_policy = policy;
+        self
     }
 
     pub(crate) fn value(&self, metric: T) -> &Histogram<u64> {
         self.inner.get(&metric).unwrap_or(&self.proto)
     }
+
+    /// Distribution and bytes values of a metric, kept apart from latency of operations
+    ///
+    /// Histogram grows with recorded values regardless of the limit of latency histogram
+    pub(crate) fn distribution(&self, metric: T) -> &Histogram<u64> {
+        self.distributions.get(&metric).unwrap_or(&self.proto)
+    }
+
+    /// Exact sum of distribution and bytes values of a metric
+    pub(crate) fn sum(&self, metric: T) -> u128 {
+        self.sums.get(&metric).copied().unwrap_or_default()
+    }
+
+    /// Number of recorded latencies, including the ones dropped above the limit of the histogram
+    pub(crate) fn count(&self, metric: T) -> u64 {
+        match self.overflow_policy {
+            OverflowPolicy::Drop => self.value(metric).len() + self.overflows(metric),
+            _ => self.value(metric).len(),
+        }
+    }
+
+    pub(crate) fn overflows(&self, metric: T) -> u64 {
+        self.overflows.get(&metric).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn counter(&self, metric: T) -> u64 {
+        self.counters.get(&metric).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn gauge(&self, metric: T) -> Option<GaugeValue> {
+        self.gauges.get(&metric).copied()
+    }
+
+    /// Sorted names of metrics with any recorded value
+    pub(crate) fn metric_names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self
+            .inner
+            .keys()
+            .chain(self.distributions.keys())
+            .chain(self.counters.keys())
+            .chain(self.gauges.keys())
+            .map(Metric::name)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Copy of the storage with values of metrics merged under a new key
+    ///
+    /// Metrics without a key are left out
+    pub(crate) fn rollup<K>(&self, key: impl Fn(&T) -> Option<K>) -> MetricAggregateStorage<K>
+    where
+        K: Metric + Send,
+    {
+        let mut storage = MetricAggregateStorage::with_proto(self.proto.clone());
+        storage.overflow_policy = self.overflow_policy;
+
+        for (metric, value) in self.overflows.iter() {
+            if let Some(key) = key(metric) {
+                *storage.overflows.entry(key).or_default() += value;
+            }
+        }
+
+        for (metric, histogram) in self.inner.iter() {
+            if let Some(key) = key(metric) {
+                match storage.inner.get_mut(&key) {
+                    Some(value) => *value += histogram,
+                    None => drop(storage.inner.insert(key, histogram.clone())),
+                }
+            }
+        }
+
+        for (metric, histogram) in self.distributions.iter() {
+            if let Some(key) = key(metric) {
+                match storage.distributions.get_mut(&key) {
+                    Some(value) => *value += histogram,
+                    None => drop(storage.distributions.insert(key, histogram.clone())),
+                }
+            }
+        }
+
+        for (metric, value) in self.sums.iter() {
+            if let Some(key) = key(metric) {
+                *storage.sums.entry(key).or_default() += value;
+            }
+        }
+
+        for (metric, value) in self.counters.iter() {
+            if let Some(key) = key(metric) {
+                *storage.counters.entry(key).or_default() += value;
+            }
+        }
+
+        for (metric, gauge) in self.gauges.iter() {
+            if let Some(key) = key(metric) {
+                match storage.gauges.get_mut(&key) {
+                    Some(value) => value.merge(*gauge),
+                    None => drop(storage.gauges.insert(key, *gauge)),
+                }
+            }
+        }
+
+        storage
+    }
 }
 
 impl<T> AggregateStorage for MetricAggregateStorage<T>
@@ -74,10 +197,31 @@ where
             .inner
             .entry(metric)
             .or_insert_with(|| self.proto.clone());
-        match histogram.record(latency_value) {
-            Ok(_) => (),
-            Err(error) => {
-                error!(latency_value = ?latency_value, error = ?error, "Failed to store latency value")
+
+        if !self.overflow_policy.record(histogram, latency_value) {
+            *self.overflows.entry(metric).or_default() += 1;
+        }
+    }
+
+    fn record_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        match value {
+            MetricValue::Counter(value) => *self.counters.entry(metric).or_default() += value,
+            MetricValue::Gauge(value) => match self.gauges.get_mut(&metric) {
+                Some(gauge) => gauge.record(value),
+                None => drop(self.gauges.insert(metric, GaugeValue::new(value))),
+            },
+            MetricValue::Distribution(value) | MetricValue::Bytes(value) => {
+                let histogram = self.distributions.entry(metric).or_insert_with(|| {
+                    let mut histogram = self.proto.clone();
+                    histogram.auto(true);
+                    histogram
+                });
+
+                if histogram.record(value).is_err() {
+                    histogram.saturating_record(value);
+                }
+
+                *self.sums.entry(metric).or_default() += value as u128;
             }
         }
     }
@@ -91,7 +235,55 @@ where
             }
         }
 
-        Self { inner, ..self }
+        let mut distributions = self.distributions;
+        for (metric, histogram) in other.distributions.into_iter() {
+            match distributions.get_mut(&metric) {
+                Some(value) => *value += histogram,
+                None => drop(distributions.insert(metric, histogram)),
+            }
+        }
+
+        let mut sums = self.sums;
+        for (metric, value) in other.sums.into_iter() {
+            *sums.entry(metric).or_default() += value;
+        }
+
+        let mut counters = self.counters;
+        for (metric, value) in other.counters.into_iter() {
+            *counters.entry(metric).or_default() += value;
+        }
+
+        let mut overflows = self.overflows;
+        for (metric, value) in other.overflows.into_iter() {
+            *overflows.entry(metric).or_default() += value;
+        }
+
+        let mut gauges = self.gauges;
+        for (metric, gauge) in other.gauges.into_iter() {
+            match gauges.get_mut(&metric) {
+                Some(value) => value.merge(gauge),
+                None => drop(gauges.insert(metric, gauge)),
+            }
+        }
+
+        Self {
+            inner,
+            distributions,
+            sums,
+            counters,
+            gauges,
+            overflows,
+            ..self
+        }
+    }
+
+    /// Nanosecond values of automatic scale exceed limits meant for coarser units,
+    /// so histogram is resized instead of overflowing
+    fn with_scale(self, scale: AggregateScale) -> Self {
+        match scale {
+            AggregateScale::Auto => self.with_overflow_policy(OverflowPolicy::Resize),
+            _ => self,
+        }
     }
 }
 
@@ -102,6 +294,12 @@ where
     fn clone(&self) -> Self {
         Self {
             inner: FxHashMap::default(),
+            distributions: FxHashMap::default(),
+            sums: FxHashMap::default(),
+            counters: FxHashMap::default(),
+            gauges: FxHashMap::default(),
+            overflows: FxHashMap::default(),
+            overflow_policy: self.overflow_policy,
             proto: self.proto.clone(),
         }
     }
@@ -162,6 +360,135 @@ mod tests {
         assert_eq!(storage.value(TestMetric::Two).min(), 20);
     }
 
+    #[test]
+    fn stores_values_by_their_kind() {
+        let mut storage = MetricAggregateStorage::default();
+        storage.record_value(TestMetric::One, MetricValue::Counter(2));
+        storage.record_value(TestMetric::One, MetricValue::Counter(3));
+        storage.record_value(TestMetric::One, MetricValue::Gauge(10));
+        storage.record_value(TestMetric::One, MetricValue::Gauge(20));
+        storage.record_value(TestMetric::Two, MetricValue::Distribution(300));
+        storage.record_value(TestMetric::Two, MetricValue::Bytes(500));
+
+        assert_eq!(storage.counter(TestMetric::One), 5);
+        assert_eq!(storage.counter(TestMetric::Two), 0);
+        assert_eq!(storage.gauge(TestMetric::One).unwrap().mean(), 15.0);
+        assert_eq!(storage.gauge(TestMetric::Two), None);
+        assert_eq!(storage.value(TestMetric::One).len(), 0);
+        assert_eq!(storage.value(TestMetric::Two).len(), 0);
+        assert_eq!(storage.distribution(TestMetric::Two).len(), 2);
+        assert_eq!(storage.distribution(TestMetric::Two).max(), 500);
+    }
+
+    #[test]
+    fn keeps_distributions_apart_from_limited_latency_histogram() {
+        let (mut one, mut two) = (
+            MetricAggregateStorage::with_limit(3, 1000).unwrap(),
+            MetricAggregateStorage::with_limit(3, 1000).unwrap(),
+        );
+
+        one.record(TestMetric::One, 100);
+        one.record_value(TestMetric::One, MetricValue::Bytes(1_000_000));
+        two.record_value(TestMetric::One, MetricValue::Distribution(3));
+
+        let merged = one.merge(two);
+
+        assert_eq!(merged.value(TestMetric::One).len(), 1);
+        assert_eq!(merged.overflows(TestMetric::One), 0);
+        assert_eq!(merged.distribution(TestMetric::One).len(), 2);
+        assert_eq!(merged.distribution(TestMetric::One).min(), 3);
+        assert!(merged.distribution(TestMetric::One).max() >= 1_000_000);
+        assert_eq!(merged.sum(TestMetric::One), 1_000_003);
+        assert_eq!(merged.clone().distribution(TestMetric::One).len(), 0);
+    }
+
+    #[test]
+    fn merges_counters_and_gauges() {
+        let (mut one, mut two) = (
+            MetricAggregateStorage::default(),
+            MetricAggregateStorage::default(),
+        );
+
+        one.record_value(TestMetric::One, MetricValue::Counter(2));
+        one.record_value(TestMetric::Two, MetricValue::Gauge(10));
+        two.record_value(TestMetric::One, MetricValue::Counter(3));
+        two.record_value(TestMetric::Two, MetricValue::Gauge(30));
+
+        let merged = one.merge(two);
+
+        assert_eq!(merged.counter(TestMetric::One), 5);
+        assert_eq!(merged.gauge(TestMetric::Two).unwrap().min(), 10);
+        assert_eq!(merged.gauge(TestMetric::Two).unwrap().max(), 30);
+    }
+
+    #[test]
+    fn rolls_up_values_under_new_key() {
+        let mut storage = MetricAggregateStorage::default();
+        storage.record(TestMetric::One, 100);
+        storage.record(TestMetric::Two, 20);
+        storage.record_value(TestMetric::One, MetricValue::Counter(2));
+        storage.record_value(TestMetric::Two, MetricValue::Counter(3));
+
+        let all = storage.rollup(|_| Some("all"));
+        let only_two = storage.rollup(|metric| (*metric == TestMetric::Two).then_some("two"));
+
+        assert_eq!(all.value("all").len(), 2);
+        assert_eq!(all.counter("all"), 5);
+        assert_eq!(only_two.value("two").max(), 20);
+        assert_eq!(only_two.counter("two"), 3);
+        assert_eq!(storage.value(TestMetric::One).len(), 1);
+    }
+
+    #[test]
+    fn lists_names_of_recorded_metrics() {
+        let mut storage = MetricAggregateStorage::default();
+        storage.record(TestMetric::Two, 20);
+        storage.record_value(TestMetric::Two, MetricValue::Counter(3));
+        storage.record_value(TestMetric::One, MetricValue::Gauge(3));
+
+        assert_eq!(storage.metric_names(), ["metric_one", "metric_two"]);
+    }
+
+    #[test]
+    fn counts_overflowing_values_per_metric() {
+        let mut storage = MetricAggregateStorage::with_limit(3, 1000).unwrap();
+        storage.record(TestMetric::One, 5000);
+        storage.record(TestMetric::One, 500);
+
+        assert_eq!(storage.overflows(TestMetric::One), 1);
+        assert_eq!(storage.overflows(TestMetric::Two), 0);
+        assert_eq!(storage.value(TestMetric::One).max(), 500);
+        assert_eq!(storage.count(TestMetric::One), 2);
+    }
+
+    #[test]
+    fn clamps_overflowing_values_into_top_bucket() {
+        let mut storage = MetricAggregateStorage::with_limit(3, 1000)
+            .unwrap()
+            .with_overflow_policy(OverflowPolicy::Clamp);
+        storage.record(TestMetric::One, 5000);
+
+        let clone = storage.clone();
+        let merged = storage.merge(MetricAggregateStorage::with_limit(3, 1000).unwrap());
+
+        assert_eq!(merged.overflows(TestMetric::One), 1);
+        assert_eq!(merged.value(TestMetric::One).len(), 1);
+        assert_eq!(merged.count(TestMetric::One), 1);
+        assert_eq!(clone.overflow_policy, OverflowPolicy::Clamp);
+        assert_eq!(clone.overflows(TestMetric::One), 0);
+    }
+
+    #[test]
+    fn resizes_histogram_for_overflowing_values() {
+        let mut storage = MetricAggregateStorage::with_limit(3, 1000)
+            .unwrap()
+            .with_overflow_policy(OverflowPolicy::Resize);
+        storage.record(TestMetric::One, 2000);
+
+        assert_eq!(storage.overflows(TestMetric::One), 0);
+        assert_eq!(storage.value(TestMetric::One).max(), 2000);
+    }
+
     #[test]
     fn modifies_proto_histogram_sigfig() {
         let storage = MetricAggregateStorage::<TestMetric>::with_sigfig(1).unwrap();
@@ -177,7 +504,7 @@ mod tests {
     #[test]
     fn modifying_max_value_disables_auto_resize() {
         let storage = MetricAggregateStorage::<TestMetric>::with_limit(3, 6100).unwrap();
-        assert_eq!(false, storage.proto.is_auto_resize())
+        assert!(!storage.proto.is_auto_resize())
     }
 
     #[test]
@@ -1,11 +1,22 @@
+pub use apdex::*;
 pub use combined::*;
+pub use gauge::*;
 pub use metric::*;
+pub use overflow::*;
+pub use sketch::*;
+pub use summary::*;
 pub use total::*;
 
-use crate::metric::Metric;
+use crate::aggregate::AggregateScale;
+use crate::metric::{Metric, MetricValue};
 
+mod apdex;
 mod combined;
+mod gauge;
 mod metric;
+mod overflow;
+mod sketch;
+mod summary;
 mod total;
 
 /// Storage for aggregation of metric values
@@ -20,6 +31,18 @@ pub trait AggregateStorage: Clone + Default + Send {
     /// * `latency_value`: latency value to be recorde in histogram
     fn record(&mut self, metric: Self::Metric, latency_value: u64);
 
+    /// Records latency of a failed operation
+    ///
+    /// By default failed operations are recorded the same way as successful ones
+    fn record_failure(&mut self, metric: Self::Metric, latency_value: u64) {
+        self.record(metric, latency_value)
+    }
+
+    /// Records non-latency value of a metric
+    ///
+    /// By default value is ignored, so it is never mixed into latency statistics
+    fn record_value(&mut self, _metric: Self::Metric, _value: MetricValue) {}
+
     /// Creates a new storage by merging together both storages
     ///
     /// # Arguments
@@ -27,6 +50,13 @@ pub trait AggregateStorage: Clone + Default + Send {
     /// * `other`: other storage of the same type
     fn merge(self, other: Self) -> Self;
 
+    /// Adapts storage to the scale of recorded latencies, applied by aggregate builders
+    ///
+    /// By default storage is left as is
+    fn with_scale(self, _scale: AggregateScale) -> Self {
+        self
+    }
+
     /// Chains another storage to store metric values in
     fn and<O>(self, other: O) -> CombinedAggregateStorage<Self, O>
     where
@@ -0,0 +1,82 @@
+use hdrhistogram::Histogram;
+use tracing::debug;
+
+/// Handling of values above the limit of a histogram
+///
+/// Overflowing values are counted per metric in every policy except for resize,
+/// which never overflows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Value is not recorded in the histogram
+    #[default]
+    Drop,
+    /// Value is recorded as the highest trackable value of the histogram
+    Clamp,
+    /// Histogram is resized to fit the value, ignoring the configured limit
+    Resize,
+}
+
+impl OverflowPolicy {
+    pub(crate) fn apply(&self, histogram: &mut Histogram<u64>) {
+        if let Self::Resize = self {
+            histogram.auto(true)
+        }
+    }
+
+    /// Records value into histogram, returns `false` when value has overflown the histogram
+    ///
+    /// Overflows are expected under drop and clamp policies and are reported
+    /// via overflow count of a metric, so each one is only logged at debug level
+    #[inline]
+    pub(crate) fn record(&self, histogram: &mut Histogram<u64>, latency_value: u64) -> bool {
+        match histogram.record(latency_value) {
+            Ok(_) => true,
+            Err(error) => {
+                debug!(latency_value = ?latency_value, error = ?error, "Latency value overflows histogram");
+
+                if let Self::Clamp = self {
+                    histogram.saturating_record(latency_value);
+                }
+
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(policy: OverflowPolicy) -> Histogram<u64> {
+        let mut histogram = Histogram::new_with_max(1000, 3).unwrap();
+        policy.apply(&mut histogram);
+        histogram
+    }
+
+    #[test]
+    fn drops_overflowing_value() {
+        let mut histogram = histogram(OverflowPolicy::Drop);
+
+        assert!(!OverflowPolicy::Drop.record(&mut histogram, 5000));
+        assert!(OverflowPolicy::Drop.record(&mut histogram, 500));
+        assert_eq!(histogram.len(), 1);
+    }
+
+    #[test]
+    fn clamps_overflowing_value_into_top_bucket() {
+        let mut histogram = histogram(OverflowPolicy::Clamp);
+
+        assert!(!OverflowPolicy::Clamp.record(&mut histogram, 5000));
+        assert_eq!(histogram.len(), 1);
+        assert_eq!(histogram.max(), histogram.high());
+    }
+
+    #[test]
+    fn resizes_histogram_for_overflowing_value() {
+        let mut histogram = histogram(OverflowPolicy::Resize);
+
+        assert!(OverflowPolicy::Resize.record(&mut histogram, 2000));
+        assert_eq!(histogram.max(), 2000);
+    }
+}
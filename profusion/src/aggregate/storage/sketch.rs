@@ -0,0 +1,448 @@
+use std::collections::BTreeMap;
+
+use rustc_hash::FxHashMap;
+
+use crate::aggregate::AggregateStorage;
+use crate::metric::{Metric, MetricValue, MetricValueKind};
+
+const DEFAULT_RELATIVE_ACCURACY: f64 = 0.01;
+const DEFAULT_MAX_BINS: usize = 2048;
+
+/// Quantile sketch with relative error guarantee (DDSketch)
+///
+/// Accepts any value without upper limit, memory is bounded by maximum number of bins
+/// by collapsing the lowest ones, so accuracy of lowest quantiles degrades first
+#[derive(Debug, Clone)]
+pub struct DDSketch {
+    gamma_ln: f64,
+    max_bins: usize,
+    bins: BTreeMap<i32, u64>,
+    zero_count: u64,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+impl DDSketch {
+    /// Creates sketch for relative accuracy within `0.0..1.0` range, e.g. `0.01` for 1% error
+    pub fn new(relative_accuracy: f64, max_bins: usize) -> Self {
+        let relative_accuracy = relative_accuracy.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+
+        Self {
+            gamma_ln: ((1.0 + relative_accuracy) / (1.0 - relative_accuracy)).ln(),
+            max_bins: max_bins.max(1),
+            bins: BTreeMap::new(),
+            zero_count: 0,
+            count: 0,
+            sum: 0,
+            min: 0,
+            max: 0,
+        }
+    }
+
+    #[inline]
+    pub fn record(&mut self, value: u64) {
+        self.min = match self.count {
+            0 => value,
+            _ => self.min.min(value),
+        };
+        self.max = self.max.max(value);
+        self.count += 1;
+        self.sum += value as u128;
+
+        match value {
+            0 => self.zero_count += 1,
+            value => {
+                *self.bins.entry(self.index(value)).or_default() += 1;
+                self.collapse();
+            }
+        }
+    }
+
+    /// Adds values of other sketch created with the same settings
+    pub fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+
+        self.min = match self.count {
+            0 => other.min,
+            _ => self.min.min(other.min),
+        };
+        self.max = self.max.max(other.max);
+        self.count += other.count;
+        self.sum += other.sum;
+        self.zero_count += other.zero_count;
+
+        for (index, count) in other.bins.iter() {
+            *self.bins.entry(*index).or_default() += count;
+        }
+
+        self.collapse();
+    }
+
+    pub fn clear(&mut self) {
+        self.bins.clear();
+        self.zero_count = 0;
+        self.count = 0;
+        self.sum = 0;
+        self.min = 0;
+        self.max = 0;
+    }
+
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn sum(&self) -> u128 {
+        self.sum
+    }
+
+    pub fn mean(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            count => self.sum as f64 / count as f64,
+        }
+    }
+
+    /// Value at percentile within `0.0..=100.0` range
+    pub fn value_at_percentile(&self, percentile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * (self.count - 1) as f64) as u64;
+
+        if rank < self.zero_count {
+            return 0;
+        }
+
+        if rank + 1 == self.count {
+            return self.max;
+        }
+
+        let mut seen = self.zero_count;
+        for (index, count) in self.bins.iter() {
+            seen += count;
+            if seen > rank {
+                return self.bin_value(*index).clamp(self.min, self.max);
+            }
+        }
+
+        self.max
+    }
+
+    #[inline]
+    fn index(&self, value: u64) -> i32 {
+        ((value as f64).ln() / self.gamma_ln).ceil() as i32
+    }
+
+    fn bin_value(&self, index: i32) -> u64 {
+        let gamma = self.gamma_ln.exp();
+        (2.0 * (index as f64 * self.gamma_ln).exp() / (1.0 + gamma)).round() as u64
+    }
+
+    fn collapse(&mut self) {
+        while self.bins.len() > self.max_bins {
+            if let Some((_, count)) = self.bins.pop_first() {
+                if let Some(lowest) = self.bins.values_mut().next() {
+                    *lowest += count;
+                }
+            }
+        }
+    }
+}
+
+impl Default for DDSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELATIVE_ACCURACY, DEFAULT_MAX_BINS)
+    }
+}
+
+/// Storage of relative-error quantile sketches per metric
+///
+/// Alternative to [`MetricAggregateStorage`](crate::aggregate::MetricAggregateStorage)
+/// for unbounded value ranges, no value is dropped due to reaching the limit
+#[derive(Debug)]
+pub struct SketchAggregateStorage<T> {
+    inner: FxHashMap<T, DDSketch>,
+    values: FxHashMap<(T, MetricValueKind), DDSketch>,
+    proto: DDSketch,
+}
+
+impl<T> Default for SketchAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    fn default() -> Self {
+        Self::with_proto(DDSketch::default())
+    }
+}
+
+impl<T> SketchAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    /// Storage with relative accuracy of quantiles and maximum number of bins per metric
+    pub fn with_accuracy(relative_accuracy: f64, max_bins: usize) -> Self {
+        Self::with_proto(DDSketch::new(relative_accuracy, max_bins))
+    }
+
+    fn with_proto(proto: DDSketch) -> Self {
+        Self {
+            inner: FxHashMap::default(),
+            values: FxHashMap::default(),
+            proto,
+        }
+    }
+
+    pub fn value(&self, metric: T) -> &DDSketch {
+        self.inner.get(&metric).unwrap_or(&self.proto)
+    }
+
+    /// Sketch of non-latency values of a kind for a metric, e.g. transferred bytes,
+    /// kept apart from latency of operations and values of other kinds
+    pub fn metric_value(&self, metric: T, kind: MetricValueKind) -> &DDSketch {
+        self.values.get(&(metric, kind)).unwrap_or(&self.proto)
+    }
+}
+
+impl<T> AggregateStorage for SketchAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    type Metric = T;
+
+    #[inline]
+    fn record(&mut self, metric: Self::Metric, latency_value: u64) {
+        self.inner
+            .entry(metric)
+            .or_insert_with(|| self.proto.clone())
+            .record(latency_value)
+    }
+
+    #[inline]
+    fn record_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        self.values
+            .entry((metric, value.kind()))
+            .or_insert_with(|| self.proto.clone())
+            .record(value.value())
+    }
+
+    fn merge(self, other: Self) -> Self {
+        let mut inner = self.inner;
+        for (metric, sketch) in other.inner.into_iter() {
+            match inner.get_mut(&metric) {
+                Some(value) => value.merge(&sketch),
+                None => drop(inner.insert(metric, sketch)),
+            }
+        }
+
+        let mut values = self.values;
+        for (key, sketch) in other.values.into_iter() {
+            match values.get_mut(&key) {
+                Some(value) => value.merge(&sketch),
+                None => drop(values.insert(key, sketch)),
+            }
+        }
+
+        Self {
+            inner,
+            values,
+            ..self
+        }
+    }
+}
+
+impl<T> Clone for SketchAggregateStorage<T>
+where
+    T: Metric,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: FxHashMap::default(),
+            values: FxHashMap::default(),
+            proto: self.proto.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_within_accuracy(actual: u64, expected: u64, accuracy: f64) {
+        let error = (actual as f64 - expected as f64).abs() / expected as f64;
+
+        assert!(
+            error <= accuracy,
+            "{actual} is not within {accuracy} of {expected}"
+        );
+    }
+
+    #[test]
+    fn calculates_percentiles_within_relative_accuracy() {
+        let mut sketch = DDSketch::default();
+
+        for value in 1..=10000 {
+            sketch.record(value);
+        }
+
+        assert_within_accuracy(sketch.value_at_percentile(50.0), 5000, 0.01);
+        assert_within_accuracy(sketch.value_at_percentile(90.0), 9000, 0.01);
+        assert_within_accuracy(sketch.value_at_percentile(99.0), 9900, 0.01);
+        assert_eq!(sketch.value_at_percentile(100.0), 10000);
+        assert_eq!(sketch.value_at_percentile(0.0), 1);
+    }
+
+    #[test]
+    fn keeps_exact_count_sum_and_bounds() {
+        let mut sketch = DDSketch::default();
+        sketch.record(0);
+        sketch.record(100);
+        sketch.record(500);
+
+        assert_eq!(sketch.len(), 3);
+        assert_eq!(sketch.min(), 0);
+        assert_eq!(sketch.max(), 500);
+        assert_eq!(sketch.mean(), 200.0);
+        assert_eq!(sketch.value_at_percentile(0.0), 0);
+    }
+
+    #[test]
+    fn accepts_values_without_upper_limit() {
+        let mut sketch = DDSketch::default();
+        sketch.record(10);
+        sketch.record(u64::MAX / 2);
+
+        assert_eq!(sketch.len(), 2);
+        assert_within_accuracy(sketch.value_at_percentile(100.0), u64::MAX / 2, 0.01);
+    }
+
+    #[test]
+    fn bounds_memory_by_collapsing_lowest_bins() {
+        let mut sketch = DDSketch::new(0.01, 64);
+
+        for value in (0..40).map(|power| 1u64 << power) {
+            sketch.record(value);
+        }
+
+        assert!(sketch.bins.len() <= 64);
+        assert_eq!(sketch.len(), 40);
+        assert_within_accuracy(sketch.value_at_percentile(100.0), 1 << 39, 0.01);
+    }
+
+    #[test]
+    fn reports_empty_sketch() {
+        let sketch = DDSketch::default();
+
+        assert!(sketch.is_empty());
+        assert_eq!(sketch.value_at_percentile(99.0), 0);
+        assert_eq!(sketch.mean(), 0.0);
+    }
+
+    #[test]
+    fn stores_sketch_per_metric() {
+        let mut storage = SketchAggregateStorage::default();
+        storage.record("one", 100);
+        storage.record("two", 20);
+        storage.record("two", 50);
+
+        assert_eq!(storage.value("one").len(), 1);
+        assert_eq!(storage.value("two").max(), 50);
+        assert_eq!(storage.value("three").len(), 0);
+    }
+
+    #[test]
+    fn merges_multiple_storages_into_one() {
+        let (mut one, mut two, three) = (
+            SketchAggregateStorage::default(),
+            SketchAggregateStorage::default(),
+            SketchAggregateStorage::default(),
+        );
+
+        one.record("one", 100);
+        one.record("two", 20);
+        two.record("one", 200);
+        two.record("one", 50);
+
+        let merged = three.merge(two.merge(one));
+
+        assert_eq!(merged.value("one").len(), 3);
+        assert_eq!(merged.value("one").min(), 50);
+        assert_eq!(merged.value("one").max(), 200);
+        assert_within_accuracy(merged.value("one").value_at_percentile(50.0), 100, 0.01);
+        assert_eq!(merged.value("two").len(), 1);
+    }
+
+    #[test]
+    fn sketches_values_apart_from_latency() {
+        let mut storage = SketchAggregateStorage::default();
+        storage.record("one", 100);
+        storage.record_value("one", MetricValue::Bytes(1_000_000));
+        storage.record_value("items", MetricValue::Distribution(3));
+
+        assert_eq!(storage.value("one").len(), 1);
+        assert_eq!(storage.value("one").max(), 100);
+        assert_eq!(storage.metric_value("one", MetricValueKind::Bytes).max(), 1_000_000);
+        assert_eq!(storage.metric_value("items", MetricValueKind::Distribution).len(), 1);
+        assert_eq!(storage.value("items").len(), 0);
+    }
+
+    #[test]
+    fn sketches_each_kind_of_values_apart() {
+        let mut storage = SketchAggregateStorage::default();
+        storage.record_value("download", MetricValue::Bytes(1_000_000));
+        storage.record_value("download", MetricValue::Distribution(3));
+
+        assert_eq!(storage.metric_value("download", MetricValueKind::Bytes).min(), 1_000_000);
+        assert_eq!(storage.metric_value("download", MetricValueKind::Distribution).max(), 3);
+        assert!(storage.metric_value("download", MetricValueKind::Gauge).is_empty());
+    }
+
+    #[test]
+    fn merges_values_of_storages() {
+        let (mut one, mut two) = (
+            SketchAggregateStorage::default(),
+            SketchAggregateStorage::default(),
+        );
+
+        one.record_value("items", MetricValue::Distribution(2));
+        two.record_value("items", MetricValue::Distribution(10));
+
+        let merged = one.merge(two);
+
+        let items = merged.metric_value("items", MetricValueKind::Distribution);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items.min(), 2);
+        assert_eq!(items.max(), 10);
+        assert!(merged
+            .clone()
+            .metric_value("items", MetricValueKind::Distribution)
+            .is_empty());
+    }
+
+    #[test]
+    fn keeps_accuracy_on_clone() {
+        let mut storage = SketchAggregateStorage::with_accuracy(0.05, 16);
+        storage.record("one", 100);
+
+        let clone = storage.clone();
+
+        assert_eq!(clone.value("one").len(), 0);
+        assert_eq!(clone.proto.max_bins, 16);
+    }
+}
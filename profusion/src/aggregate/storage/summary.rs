@@ -0,0 +1,289 @@
+use std::cmp::{max, min};
+
+use rustc_hash::FxHashMap;
+
+use crate::aggregate::AggregateStorage;
+use crate::metric::{Metric, MetricValue, MetricValueKind};
+
+/// Count, sum, minimum and maximum of recorded values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SummaryValue {
+    count: u64,
+    sum: u128,
+    sum_of_squares: u128,
+    min: u64,
+    max: u64,
+}
+
+impl SummaryValue {
+    #[inline]
+    fn record(&mut self, value: u64) {
+        self.merge(Self {
+            count: 1,
+            sum: value as u128,
+            sum_of_squares: value as u128 * value as u128,
+            min: value,
+            max: value,
+        })
+    }
+
+    #[inline]
+    fn merge(&mut self, other: Self) {
+        if other.count == 0 {
+            return;
+        }
+
+        self.min = match self.count {
+            0 => other.min,
+            _ => min(self.min, other.min),
+        };
+        self.max = max(self.max, other.max);
+        self.count = self.count.saturating_add(other.count);
+        self.sum = self.sum.saturating_add(other.sum);
+        self.sum_of_squares = self.sum_of_squares.saturating_add(other.sum_of_squares);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> u128 {
+        self.sum
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            count => self.sum as f64 / count as f64,
+        }
+    }
+
+    /// Population standard deviation of recorded values
+    pub fn std_dev(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            count => {
+                let mean = self.mean();
+                (self.sum_of_squares as f64 / count as f64 - mean * mean).max(0.0).sqrt()
+            }
+        }
+    }
+}
+
+/// Lightweight storage keeping only summary of values per metric
+///
+/// Suited for high-cardinality metrics or short windows when only throughput
+/// and average are needed, as no histogram is allocated per metric
+#[derive(Debug)]
+pub struct SummaryAggregateStorage<T> {
+    inner: FxHashMap<T, SummaryValue>,
+    values: FxHashMap<(T, MetricValueKind), SummaryValue>,
+}
+
+impl<T> Default for SummaryAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    fn default() -> Self {
+        Self {
+            inner: FxHashMap::default(),
+            values: FxHashMap::default(),
+        }
+    }
+}
+
+impl<T> SummaryAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    pub fn value(&self, metric: T) -> SummaryValue {
+        self.inner.get(&metric).copied().unwrap_or_default()
+    }
+
+    /// Summary of non-latency values of a kind for a metric, e.g. sum of counter increments
+    /// or mean of gauge samples, kept apart from latency of operations and values of other kinds
+    pub fn metric_value(&self, metric: T, kind: MetricValueKind) -> SummaryValue {
+        self.values.get(&(metric, kind)).copied().unwrap_or_default()
+    }
+}
+
+impl<T> AggregateStorage for SummaryAggregateStorage<T>
+where
+    T: Metric + Send,
+{
+    type Metric = T;
+
+    #[inline]
+    fn record(&mut self, metric: Self::Metric, latency_value: u64) {
+        self.inner.entry(metric).or_default().record(latency_value)
+    }
+
+    #[inline]
+    fn record_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        self.values
+            .entry((metric, value.kind()))
+            .or_default()
+            .record(value.value())
+    }
+
+    fn merge(self, other: Self) -> Self {
+        let mut inner = self.inner;
+        for (metric, value) in other.inner.into_iter() {
+            inner.entry(metric).or_default().merge(value);
+        }
+
+        let mut values = self.values;
+        for (key, value) in other.values.into_iter() {
+            values.entry(key).or_default().merge(value);
+        }
+
+        Self { inner, values }
+    }
+}
+
+impl<T> Clone for SummaryAggregateStorage<T>
+where
+    T: Metric,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: FxHashMap::default(),
+            values: FxHashMap::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_values_per_metric() {
+        let mut storage = SummaryAggregateStorage::default();
+        storage.record("one", 100);
+        storage.record("one", 300);
+        storage.record("two", 20);
+
+        let value = storage.value("one");
+
+        assert_eq!(value.count(), 2);
+        assert_eq!(value.sum(), 400);
+        assert_eq!(value.min(), 100);
+        assert_eq!(value.max(), 300);
+        assert_eq!(value.mean(), 200.0);
+        assert_eq!(value.std_dev(), 100.0);
+        assert_eq!(storage.value("two").min(), 20);
+    }
+
+    #[test]
+    fn reports_empty_summary_for_missing_metric() {
+        let storage = SummaryAggregateStorage::<&str>::default();
+
+        assert_eq!(storage.value("one").count(), 0);
+        assert_eq!(storage.value("one").mean(), 0.0);
+        assert_eq!(storage.value("one").std_dev(), 0.0);
+    }
+
+    #[test]
+    fn merges_multiple_storages_into_one() {
+        let (mut one, mut two, three) = (
+            SummaryAggregateStorage::default(),
+            SummaryAggregateStorage::default(),
+            SummaryAggregateStorage::default(),
+        );
+
+        one.record("one", 100);
+        one.record("two", 50);
+        two.record("one", 20);
+        two.record("one", 200);
+
+        let merged = three.merge(two.merge(one));
+
+        assert_eq!(merged.value("one").count(), 3);
+        assert_eq!(merged.value("one").min(), 20);
+        assert_eq!(merged.value("one").max(), 200);
+        assert_eq!(merged.value("two").sum(), 50);
+    }
+
+    #[test]
+    fn saturates_sums_of_values_near_maximum() {
+        let mut storage = SummaryAggregateStorage::default();
+        storage.record_value("download", MetricValue::Bytes(u64::MAX));
+        storage.record_value("download", MetricValue::Bytes(u64::MAX));
+
+        let value = storage.metric_value("download", MetricValueKind::Bytes);
+
+        assert_eq!(value.count(), 2);
+        assert_eq!(value.sum(), 2 * u64::MAX as u128);
+        assert_eq!(value.max(), u64::MAX);
+        assert_eq!(value.mean(), u64::MAX as f64);
+    }
+
+    #[test]
+    fn summarizes_values_apart_from_latency() {
+        let mut storage = SummaryAggregateStorage::default();
+        storage.record("one", 100);
+        storage.record_value("one", MetricValue::Bytes(1500));
+        storage.record_value("hits", MetricValue::Counter(2));
+        storage.record_value("hits", MetricValue::Counter(3));
+        storage.record_value("queue", MetricValue::Gauge(4));
+
+        assert_eq!(storage.value("one").count(), 1);
+        assert_eq!(storage.value("one").max(), 100);
+        assert_eq!(storage.metric_value("one", MetricValueKind::Bytes).sum(), 1500);
+        assert_eq!(storage.metric_value("hits", MetricValueKind::Counter).sum(), 5);
+        assert_eq!(storage.value("hits").count(), 0);
+        assert_eq!(storage.metric_value("queue", MetricValueKind::Gauge).mean(), 4.0);
+    }
+
+    #[test]
+    fn summarizes_each_kind_of_values_apart() {
+        let mut storage = SummaryAggregateStorage::default();
+        storage.record_value("download", MetricValue::Bytes(1500));
+        storage.record_value("download", MetricValue::Distribution(3));
+
+        assert_eq!(storage.metric_value("download", MetricValueKind::Bytes).sum(), 1500);
+        assert_eq!(storage.metric_value("download", MetricValueKind::Distribution).sum(), 3);
+        assert_eq!(storage.metric_value("download", MetricValueKind::Counter).count(), 0);
+    }
+
+    #[test]
+    fn merges_values_of_storages() {
+        let (mut one, mut two) = (
+            SummaryAggregateStorage::default(),
+            SummaryAggregateStorage::default(),
+        );
+
+        one.record_value("items", MetricValue::Distribution(2));
+        two.record_value("items", MetricValue::Distribution(10));
+
+        let merged = one.merge(two);
+
+        let items = merged.metric_value("items", MetricValueKind::Distribution);
+        assert_eq!(items.count(), 2);
+        assert_eq!(items.min(), 2);
+        assert_eq!(items.max(), 10);
+        assert_eq!(merged.value("items").count(), 0);
+    }
+
+    #[test]
+    fn clones_without_values() {
+        let mut storage = SummaryAggregateStorage::default();
+        storage.record("one", 100);
+        storage.record_value("one", MetricValue::Counter(1));
+
+        assert_eq!(storage.clone().value("one").count(), 0);
+        assert_eq!(
+            storage.clone().metric_value("one", MetricValueKind::Counter).count(),
+            0
+        );
+    }
+}
@@ -1,13 +1,13 @@
 use std::marker::PhantomData;
 
 pub use hdrhistogram::{CreationError, Histogram};
-use tracing::error;
-
-use crate::aggregate::AggregateStorage;
+use crate::aggregate::{AggregateScale, AggregateStorage, OverflowPolicy};
 use crate::metric::Metric;
 
 pub struct TotalAggregateStorage<T> {
     inner: Histogram<u64>,
+    overflows: u64,
+    overflow_policy: OverflowPolicy,
     _metric: PhantomData<T>,
 }
 
@@ -16,12 +16,9 @@ where
     T: Metric + Send,
 {
     fn default() -> Self {
-        Self {
-            inner: match Histogram::new(3) {
-                Ok(histogram) => histogram,
-                Err(_) => unreachable!(),
-            },
-            _metric: PhantomData,
+        match Self::with_sigfig(3) {
+            Ok(storage) => storage,
+            Err(_) => unreachable!(),
         }
     }
 }
@@ -33,24 +30,47 @@ where
     pub fn with_limit(sigfig: u8, max_value: u64) -> Result<Self, CreationError> {
         let inner = Histogram::new_with_max(max_value, sigfig)?;
 
-        Ok(Self {
-            inner,
-            _metric: PhantomData,
-        })
+        Ok(Self::with_histogram(inner))
     }
 
     pub fn with_sigfig(sigfig: u8) -> Result<Self, CreationError> {
         let inner = Histogram::new(sigfig)?;
 
-        Ok(Self {
+        Ok(Self::with_histogram(inner))
+    }
+
+    fn with_histogram(inner: Histogram<u64>) -> Self {
+        Self {
             inner,
+            overflows: 0,
+            overflow_policy: OverflowPolicy::default(),
             _metric: PhantomData,
-        })
+        }
+    }
+
+    /// Changes handling of values above the limit set via [`Self::with_limit`]
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        policy.apply(&mut self.inner);
+        self.overflow_policy = policy;
+        self
     }
 
     pub fn value(&self) -> &Histogram<u64> {
         &self.inner
     }
+
+    /// Number of recorded latencies, including the ones dropped above the limit of the histogram
+    pub fn count(&self) -> u64 {
+        match self.overflow_policy {
+            OverflowPolicy::Drop => self.inner.len() + self.overflows,
+            _ => self.inner.len(),
+        }
+    }
+
+    /// Number of values above the limit of the histogram
+    pub fn overflows(&self) -> u64 {
+        self.overflows
+    }
 }
 
 impl<T> AggregateStorage for TotalAggregateStorage<T>
@@ -61,20 +81,27 @@ where
 
     #[inline]
     fn record(&mut self, _metric: Self::Metric, latency_value: u64) {
-        match self.inner.record(latency_value) {
-            Ok(_) => (),
-            Err(error) => {
-                error!(latency_value = ?latency_value, error = ?error, "Failed to store latency value")
-            }
+        if !self.overflow_policy.record(&mut self.inner, latency_value) {
+            self.overflows += 1;
         }
     }
 
     fn merge(self, other: Self) -> Self {
         Self {
             inner: self.inner + other.inner,
+            overflows: self.overflows + other.overflows,
             ..self
         }
     }
+
+    /// Nanosecond values of automatic scale exceed limits meant for coarser units,
+    /// so histogram is resized instead of overflowing
+    fn with_scale(self, scale: AggregateScale) -> Self {
+        match scale {
+            AggregateScale::Auto => self.with_overflow_policy(OverflowPolicy::Resize),
+            _ => self,
+        }
+    }
 }
 
 impl<T> Clone for TotalAggregateStorage<T>
@@ -87,6 +114,8 @@ where
 
         Self {
             inner,
+            overflows: 0,
+            overflow_policy: self.overflow_policy,
             _metric: PhantomData,
         }
     }
@@ -145,6 +174,25 @@ mod tests {
         assert_eq!(merged.value().count_at(50), 3)
     }
 
+    #[test]
+    fn counts_overflowing_values() {
+        let (mut one, mut two) = (
+            TotalAggregateStorage::with_limit(3, 1000).unwrap(),
+            TotalAggregateStorage::with_limit(3, 1000)
+                .unwrap()
+                .with_overflow_policy(OverflowPolicy::Clamp),
+        );
+
+        one.record(TestMetric::One, 5000);
+        two.record(TestMetric::Two, 5000);
+
+        assert_eq!(one.value().len(), 0);
+        assert_eq!(two.value().len(), 1);
+        assert_eq!(one.count(), 1);
+        assert_eq!(two.count(), 1);
+        assert_eq!(one.merge(two).overflows(), 2);
+    }
+
     #[test]
     fn modifies_proto_histogram_sigfig() {
         let storage = TotalAggregateStorage::<TestMetric>::with_sigfig(1).unwrap();
@@ -160,6 +208,6 @@ mod tests {
     #[test]
     fn modifying_max_value_disables_auto_resize() {
         let storage = TotalAggregateStorage::<TestMetric>::with_limit(1, 6100).unwrap();
-        assert_eq!(false, storage.inner.is_auto_resize())
+        assert!(!storage.inner.is_auto_resize())
     }
 }
@@ -1,7 +1,7 @@
 use std::{marker::PhantomData, time::Duration};
 
 use crate::aggregate::{MetricAggregate, MetricAggregateBuilder};
-use crate::metric::{Metric, MetricRecordError};
+use crate::metric::{Metric, MetricRecordError, MetricValue};
 
 /// Test aggregate builder
 ///
@@ -13,6 +13,8 @@ pub struct TestAggregateBuilder<T>(PhantomData<T>);
 /// Adds reported metrics to vector for later verification in tests
 pub struct TestAggregate<T> {
     values: Vec<(T, Duration, bool)>,
+    metric_values: Vec<(T, MetricValue)>,
+    relations: Vec<(String, String)>,
 }
 
 impl<T> TestAggregateBuilder<T> {
@@ -21,6 +23,12 @@ impl<T> TestAggregateBuilder<T> {
     }
 }
 
+impl<T> Default for TestAggregateBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> MetricAggregateBuilder for TestAggregateBuilder<T>
 where
     T: Metric,
@@ -28,7 +36,11 @@ where
     type Reporter = TestAggregate<T>;
 
     fn build(&self) -> Self::Reporter {
-        TestAggregate { values: Vec::new() }
+        TestAggregate {
+            values: Vec::new(),
+            metric_values: Vec::new(),
+            relations: Vec::new(),
+        }
     }
 }
 
@@ -39,6 +51,19 @@ where
     pub fn values(self) -> Vec<(T, Duration, bool)> {
         self.values
     }
+
+    /// Non-latency values in order of recording
+    pub fn metric_values(&self) -> &[(T, MetricValue)] {
+        &self.metric_values
+    }
+
+    /// Child and parent metric names in order of recording
+    pub fn relations(&self) -> Vec<(&str, &str)> {
+        self.relations
+            .iter()
+            .map(|(child, parent)| (child.as_str(), parent.as_str()))
+            .collect()
+    }
 }
 
 impl<T> MetricAggregate for TestAggregate<T>
@@ -56,7 +81,17 @@ where
         self.values.push((metric, elapsed, error.is_some()))
     }
 
+    fn add_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        self.metric_values.push((metric, value))
+    }
+
+    fn add_relation(&mut self, child: &str, parent: &str) {
+        self.relations.push((child.to_string(), parent.to_string()))
+    }
+
     fn merge_into(mut self, other: &mut Self) {
         other.values.append(&mut self.values);
+        other.metric_values.append(&mut self.metric_values);
+        other.relations.append(&mut self.relations);
     }
 }
@@ -1,47 +1,18 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
-use crate::metric::MetricRecordError;
-use crate::prelude::*;
-
-struct Counter(Arc<AtomicUsize>);
-
-impl Counter {
-    fn new() -> Self {
-        Self(Arc::new(AtomicUsize::new(0)))
-    }
-
-    fn increment(&self) {
-        self.0.fetch_add(1, Ordering::Relaxed);
-    }
-
-    fn decrement(&self) {
-        self.0.fetch_sub(1, Ordering::Relaxed);
-    }
-
-    fn current(&self) -> usize {
-        self.0.load(Ordering::Relaxed)
-    }
-}
+use rustc_hash::{FxHashMap, FxHashSet};
 
-impl Clone for Counter {
-    fn clone(&self) -> Self {
-        self.increment();
-        Self(self.0.clone())
-    }
-}
+use crate::metric::{MetricRecordError, MetricValue};
+use crate::prelude::*;
 
-impl Drop for Counter {
-    fn drop(&mut self) {
-        self.decrement()
-    }
-}
+use super::counter::{Activity, Counter};
 
 pub struct TimelineAggregateBuilder<S> {
     settings: AggregateSettings,
     storage: S,
     users: Counter,
+    in_flight: Option<InFlightTracker>,
 }
 
 pub struct TimelineAggregate<S> {
@@ -50,6 +21,9 @@ pub struct TimelineAggregate<S> {
     storage: S,
     total: TimelineItem<S>,
     users: Counter,
+    in_flight: Option<InFlightTracker>,
+    drained_until: Duration,
+    relations: FxHashMap<Box<str>, FxHashSet<Box<str>>>,
 }
 
 impl<S> TimelineAggregateBuilder<S>
@@ -64,35 +38,51 @@ where
     pub fn with_settings(storage: S, settings: AggregateSettings) -> Self {
         Self {
             settings,
-            storage,
-            users: Counter::new(),
+            storage: storage.with_scale(settings.scale()),
+            users: Counter::new(*settings.zero(), *settings.window(), settings.retention()),
+            in_flight: settings.in_flight().then(|| {
+                InFlightTracker::new(*settings.zero(), *settings.window(), settings.retention())
+            }),
         }
     }
-}
 
-impl<S> MetricAggregateBuilder for TimelineAggregateBuilder<S>
-where
-    S: AggregateStorage,
-    S::Metric: Sync,
-{
-    type Reporter = TimelineAggregate<S>;
+    /// Creates aggregate for collecting results that is not counted as an active user
+    pub(crate) fn build_collector(&self) -> TimelineAggregate<S> {
+        self.create(self.users.observer())
+    }
 
-    fn build(&self) -> Self::Reporter {
+    fn create(&self, users: Counter) -> TimelineAggregate<S> {
         TimelineAggregate {
             timeline: Vec::new(),
             storage: self.storage.clone(),
             total: TimelineItem::new(
                 self.settings.zero().window(self.settings.window()),
+                Duration::ZERO,
                 self.storage.clone(),
                 0,
                 0,
             ),
             settings: self.settings,
-            users: self.users.clone(),
+            users,
+            in_flight: self.in_flight.clone(),
+            drained_until: Duration::ZERO,
+            relations: FxHashMap::default(),
         }
     }
 }
 
+impl<S> MetricAggregateBuilder for TimelineAggregateBuilder<S>
+where
+    S: AggregateStorage,
+    S::Metric: Sync,
+{
+    type Reporter = TimelineAggregate<S>;
+
+    fn build(&self) -> Self::Reporter {
+        self.create(self.users.clone())
+    }
+}
+
 impl<S> MetricAggregate for TimelineAggregate<S>
 where
     S: AggregateStorage,
@@ -105,49 +95,403 @@ where
         latency: Duration,
         error: Option<&MetricRecordError>,
     ) {
+        let latency = self.settings.scale().duration_to_value(latency);
+        let users = self.users.current();
+        let item = self.current_item();
+        item.record_entry(metric, latency, error);
+        item.update_counters(error, users);
+        self.total.record_entry(metric, latency, error);
+        self.total.update_counters(error, users);
+    }
+
+    fn add_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        self.current_item().record_value(metric, value);
+        self.total.record_value(metric, value);
+    }
+
+    fn add_relation(&mut self, child: &str, parent: &str) {
+        let recorded = self
+            .relations
+            .get(child)
+            .is_some_and(|parents| parents.contains(parent));
+
+        if !recorded {
+            self.relations
+                .entry(child.into())
+                .or_default()
+                .insert(parent.into());
+        }
+    }
+
+    fn in_flight(&self) -> Option<&InFlightTracker> {
+        self.in_flight.as_ref()
+    }
+
+    fn merge_into(self, other: &mut Self) {
+        for (child, parents) in self.relations {
+            other.relations.entry(child).or_default().extend(parents);
+        }
+        self.total.merge_into(&mut other.total);
+        other.drained_until = other.drained_until.max(self.drained_until);
+        other.merge_timeline(self.timeline);
+    }
+}
+
+impl<S> TimelineAggregate<S>
+where
+    S: AggregateStorage,
+{
+    /// Completes the aggregate with number of users and in-flight operations
+    /// within each time window
+    pub fn flush(mut self) -> (TimelineItem<S>, Vec<TimelineItem<S>>) {
+        self.fill_empty_windows(self.settings.zero().window(self.settings.window()));
+        self.apply_retention();
+
+        let mut timeline = std::mem::take(&mut self.timeline);
+        self.update_activity(&mut timeline);
+        self.update_activity_of_total();
+
+        (self.total, timeline)
+    }
+
+    /// Child and parent metric names of measurements recorded within transactions
+    pub fn relations(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.relations.iter().flat_map(|(child, parents)| {
+            parents.iter().map(move |parent| (child.as_ref(), parent.as_ref()))
+        })
+    }
+
+    /// Removes windows that are not going to receive new entries from the timeline
+    ///
+    /// Allows to periodically pass completed windows to another destination,
+    /// so memory usage of long-running aggregate stays bounded.
+    ///
+    /// Users and in-flight operations of drained windows are discarded, so they are
+    /// not available to other aggregates of the same builder that still have windows
+    /// in that time range.
+    pub fn drain_closed(&mut self) -> Vec<TimelineItem<S>> {
+        let mut drained = self.drain_closed_windows();
+        self.update_activity(&mut drained);
+
+        let until = self.drained_until.saturating_sub(*self.settings.window() / 2);
+        self.users.discard_until(until);
+        if let Some(in_flight) = &self.in_flight {
+            in_flight.discard_until(until);
+        }
+
+        drained
+    }
+
+    fn drain_closed_windows(&mut self) -> Vec<TimelineItem<S>> {
+        let time_window = self.settings.zero().window(self.settings.window());
+        let closed = self
+            .timeline
+            .partition_point(|item| *item.time() < time_window);
+
+        let drained: Vec<_> = self.timeline.drain(..closed).collect();
+        if let Some(last) = drained.last() {
+            self.drained_until = self.drained_until.max(*last.time() + *last.window());
+        }
+
+        drained
+    }
+
+    /// Updates items with users active within their time span, including users without entries,
+    /// and with operations in-flight within their time span
+    ///
+    /// Entries are assigned to the closest time window, so time span of an item
+    /// starts half of the window before its time
+    fn update_activity(&self, timeline: &mut [TimelineItem<S>]) {
+        let offset = *self.settings.window() / 2;
+        let spans: Vec<_> = timeline
+            .iter()
+            .map(|item| {
+                (
+                    item.time().saturating_sub(offset),
+                    (*item.time() + *item.window()).saturating_sub(offset),
+                )
+            })
+            .collect();
+
+        let activities = self.users.activities(spans.iter().copied());
+        for (item, activity) in timeline.iter_mut().zip(activities) {
+            if let Some(activity) = activity {
+                item.update_users(activity);
+            }
+        }
+
+        for (metric, activities) in self.in_flight_activities(&spans) {
+            for (item, activity) in timeline.iter_mut().zip(activities) {
+                if let Some(activity) = activity {
+                    item.update_concurrency(metric.clone(), activity);
+                }
+            }
+        }
+    }
+
+    fn update_activity_of_total(&mut self) {
+        let spans = [(Duration::ZERO, Duration::MAX)];
+
+        if let Some(activity) = self.users.activity(Duration::ZERO, Duration::MAX) {
+            self.total.update_users(activity);
+        }
+
+        for (metric, activities) in self.in_flight_activities(&spans) {
+            if let Some(activity) = activities[0] {
+                self.total.update_concurrency(metric, activity);
+            }
+        }
+    }
+
+    fn in_flight_activities(
+        &self,
+        spans: &[(Duration, Duration)],
+    ) -> Vec<(Arc<str>, Vec<Option<Activity>>)> {
+        self.in_flight
+            .as_ref()
+            .map(|in_flight| in_flight.activities(spans))
+            .unwrap_or_default()
+    }
+
+    /// Merges timeline items into the timeline of aggregate
+    fn merge_timeline(&mut self, timeline: Vec<TimelineItem<S>>) {
+        for item in timeline.into_iter() {
+            let position = self.timeline.partition_point(|existing| existing.time() <= item.time());
+
+            match position.checked_sub(1).map(|covering| &mut self.timeline[covering]) {
+                Some(covering) if *item.time() < *covering.time() + *covering.window() => {
+                    item.merge_into(covering)
+                }
+                _ => self.timeline.insert(position, item),
+            }
+        }
+
+        self.fill_empty_windows(Duration::ZERO);
+        self.apply_retention();
+    }
+
+    /// Takes closed windows with totals and relations recorded so far into a new aggregate
+    ///
+    /// Returned aggregate is not counted as an active user.
+    pub(crate) fn take_closed(&mut self) -> Option<Self> {
+        let drained_until = self.drained_until;
+        let timeline = self.drain_closed_windows();
+        if timeline.is_empty() {
+            return None;
+        }
+
+        let mut closed = self.take(timeline);
+        closed.drained_until = drained_until;
+        Some(closed)
+    }
+
+    /// Takes all windows with totals and relations recorded so far into a new aggregate
+    ///
+    /// Returned aggregate is not counted as an active user.
+    pub(crate) fn take_all(&mut self) -> Self {
+        let timeline = std::mem::take(&mut self.timeline);
+        self.take(timeline)
+    }
+
+    fn take(&mut self, timeline: Vec<TimelineItem<S>>) -> Self {
+        let total = TimelineItem::new(
+            self.settings.zero().window(self.settings.window()),
+            Duration::ZERO,
+            self.storage.clone(),
+            0,
+            0,
+        );
+
+        Self {
+            settings: self.settings,
+            timeline,
+            storage: self.storage.clone(),
+            total: std::mem::replace(&mut self.total, total),
+            users: self.users.observer(),
+            in_flight: self.in_flight.clone(),
+            drained_until: self.drained_until,
+            relations: std::mem::take(&mut self.relations),
+        }
+    }
+
+    /// Item of the window that receives entries at the moment
+    fn current_item(&mut self) -> &mut TimelineItem<S> {
         let time_window = self.settings.zero().window(self.settings.window());
-        let item = match self.timeline.last_mut() {
-            Some(item) if item.time().eq(&time_window) => item,
+        self.total.extend_until(time_window + *self.settings.window());
+
+        match self.timeline.last() {
+            Some(item) if item.time().eq(&time_window) => (),
             _ => {
-                let position = self.timeline.len();
+                self.push_empty_windows(time_window);
                 self.timeline.push(TimelineItem::new(
                     time_window,
+                    *self.settings.window(),
                     self.storage.clone(),
                     0,
                     0,
                 ));
-                &mut self.timeline[position]
+                self.apply_retention();
             }
+        }
+
+        let position = self.timeline.len() - 1;
+        &mut self.timeline[position]
+    }
+
+    fn empty_item(&self, time: Duration, users: usize) -> TimelineItem<S> {
+        TimelineItem::new(time, *self.settings.window(), self.storage.clone(), 0, users)
+    }
+
+    /// Appends empty items for windows between the last item and provided time window
+    fn push_empty_windows(&mut self, time_window: Duration) {
+        let window = *self.settings.window();
+        if window.is_zero() {
+            return;
+        }
+
+        let (mut time, users) = match (self.settings.empty_windows(), self.timeline.last()) {
+            (EmptyWindows::Skip, _) | (EmptyWindows::BetweenEntries, None) => return,
+            (_, Some(last)) => (*last.time() + *last.window(), last.users()),
+            (EmptyWindows::WholeRun, None) => (self.drained_until, 0),
         };
 
-        let latency = self.settings.scale().duration_to_value(latency);
-        item.record(metric, latency);
-        item.update_counters(error, self.users.current());
-        self.total.record(metric, latency);
-        self.total.update_counters(error, self.users.current());
+        while time < time_window {
+            self.timeline.push(self.empty_item(time, users));
+            time += window;
+        }
     }
 
-    fn merge_into(self, other: &mut Self) {
-        for item in self.timeline.into_iter() {
-            match other.timeline.binary_search(&item) {
-                Ok(position) => {
-                    item.merge_into(&mut other.timeline[position]);
+    /// Inserts empty items for all windows missing in the timeline
+    ///
+    /// Users count of an empty item is carried forward from the preceding item.
+    ///
+    /// # Arguments
+    ///
+    /// * `end`: last time window to fill when the whole run is represented
+    fn fill_empty_windows(&mut self, end: Duration) {
+        let window = *self.settings.window();
+        let (mut time, end) = match self.settings.empty_windows() {
+            _ if window.is_zero() => return,
+            EmptyWindows::Skip => return,
+            EmptyWindows::BetweenEntries => match self.timeline.first() {
+                Some(first) => (*first.time(), Duration::ZERO),
+                None => return,
+            },
+            EmptyWindows::WholeRun => (self.drained_until, end),
+        };
+
+        let mut users = 0;
+        let mut timeline = Vec::with_capacity(self.timeline.len());
+        for item in std::mem::take(&mut self.timeline) {
+            while time < *item.time() {
+                timeline.push(self.empty_item(time, users));
+                time += window;
+            }
+
+            users = item.users();
+            time = time.max(*item.time() + *item.window());
+            timeline.push(item);
+        }
+
+        while time <= end {
+            timeline.push(self.empty_item(time, users));
+            time += window;
+        }
+
+        self.timeline = timeline;
+    }
+
+    /// Merges windows outside of full resolution range into coarser buckets
+    ///
+    /// Every level keeps `full_resolution` buckets and merges older ones into buckets
+    /// `factor` times larger, so number of items grows only logarithmically with run time.
+    /// Items overlapping in time, e.g. fine windows merged from another aggregate
+    /// into an already downsampled range, are merged into the covering bucket.
+    fn apply_retention(&mut self) {
+        let window = *self.settings.window();
+        let (full_resolution, factor) = match self.settings.retention() {
+            TimelineRetention::Downsample(downsample) if !window.is_zero() => {
+                (downsample.full_resolution(), downsample.factor())
+            }
+            _ => return,
+        };
+
+        let levels = match self.timeline.last() {
+            Some(latest) => retention_levels(*latest.time(), window, full_resolution, factor),
+            None => return,
+        };
+
+        let mut buckets: Vec<(Duration, Duration, TimelineItem<S>)> =
+            Vec::with_capacity(self.timeline.len());
+
+        for item in std::mem::take(&mut self.timeline) {
+            let time = *item.time();
+            let bucket = levels
+                .iter()
+                .find(|(bound, _)| time >= *bound)
+                .map_or(window, |(_, bucket)| *bucket);
+
+            let (start, end) = match bucket {
+                bucket if bucket > *item.window() => {
+                    let start = align_down(time, bucket);
+                    (start, start + bucket)
                 }
-                Err(position) => other.timeline.insert(position, item),
+                _ => (time, time + *item.window()),
+            };
+
+            match buckets.last_mut() {
+                Some((_, last_end, last)) if time < *last_end => {
+                    *last_end = (*last_end).max(end);
+                    item.merge_into(last);
+                }
+                _ => buckets.push((start, end, item)),
             }
         }
+
+        self.timeline = buckets
+            .into_iter()
+            .map(|(start, end, mut item)| {
+                item.realign(start, end - start);
+                item
+            })
+            .collect();
     }
 }
 
-impl<S> TimelineAggregate<S>
-where
-    S: AggregateStorage,
-{
-    pub fn flush(self) -> (TimelineItem<S>, Vec<TimelineItem<S>>) {
-        (self.total, self.timeline)
+/// Lower time bounds of retention levels with bucket size of each level
+///
+/// Items starting at or after the bound of a level belong to it,
+/// the last level always starts at zero time.
+pub(super) fn retention_levels(
+    latest: Duration,
+    window: Duration,
+    full_resolution: usize,
+    factor: u32,
+) -> Vec<(Duration, Duration)> {
+    let kept = u32::try_from(full_resolution).unwrap_or(u32::MAX);
+    let mut bound = latest.saturating_sub(window.saturating_mul(kept - 1));
+    let mut bucket = window;
+    let mut levels = Vec::new();
+
+    loop {
+        let next = bucket.checked_mul(factor);
+        let start = next.map_or(Duration::ZERO, |next| align_down(bound, next));
+        levels.push((start, bucket));
+
+        match next {
+            Some(next) if !start.is_zero() => {
+                bound = start.saturating_sub(next.saturating_mul(kept));
+                bucket = next;
+            }
+            _ => return levels,
+        }
     }
 }
 
+pub(super) fn align_down(time: Duration, bucket: Duration) -> Duration {
+    Duration::from_nanos((time.as_nanos() / bucket.as_nanos() * bucket.as_nanos()) as u64)
+}
+
 impl<L, R> TimelineAggregate<CombinedAggregateStorage<L, R>>
 where
     L: AggregateStorage,
@@ -162,18 +506,24 @@ where
 
         (
             TimelineAggregate {
-                users: self.users.clone(),
+                users: self.users.observer(),
+                in_flight: self.in_flight.clone(),
                 settings: self.settings,
                 total: left_total,
                 storage: left_storage,
                 timeline: left_timeline,
+                drained_until: self.drained_until,
+                relations: self.relations.clone(),
             },
             TimelineAggregate {
-                users: self.users.clone(),
+                users: self.users,
+                in_flight: self.in_flight,
                 settings: self.settings,
                 total: right_total,
                 storage: right_storage,
                 timeline: right_timeline,
+                drained_until: self.drained_until,
+                relations: self.relations,
             },
         )
     }
@@ -185,7 +535,7 @@ mod tests {
 
     use super::*;
 
-    #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+    #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
     enum ReportMetric {
         One,
         Two,
@@ -320,6 +670,310 @@ mod tests {
         );
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn fills_empty_windows_between_entries() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_empty_windows(EmptyWindows::BetweenEntries),
+        );
+        let mut reporter = builder.build();
+        let other = builder.build();
+
+        populate_test_metric(
+            &mut reporter,
+            vec![
+                Action::Wait(Duration::from_millis(100)),
+                Action::Add(ReportMetric::One, Duration::from_millis(10)),
+                Action::Wait(Duration::from_millis(300)),
+                Action::Add(ReportMetric::Two, Duration::from_millis(20)),
+                Action::Wait(Duration::from_millis(300)),
+            ],
+        )
+        .await;
+        drop(other);
+
+        verify_timeline(
+            vec![
+                (Duration::from_millis(100), (10, 0), 0, 2),
+                (Duration::from_millis(200), (0, 0), 0, 2),
+                (Duration::from_millis(300), (0, 0), 0, 2),
+                (Duration::from_millis(400), (0, 20), 0, 2),
+            ],
+            reporter.flush().1,
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fills_empty_windows_for_whole_run_on_flush() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_empty_windows(EmptyWindows::WholeRun),
+        );
+        let mut reporter = builder.build();
+
+        populate_test_metric(
+            &mut reporter,
+            vec![
+                Action::Wait(Duration::from_millis(200)),
+                Action::Add(ReportMetric::One, Duration::from_millis(10)),
+                Action::Wait(Duration::from_millis(200)),
+            ],
+        )
+        .await;
+
+        verify_timeline(
+            vec![
+                (Duration::from_millis(0), (0, 0), 0, 1),
+                (Duration::from_millis(100), (0, 0), 0, 1),
+                (Duration::from_millis(200), (10, 0), 0, 1),
+                (Duration::from_millis(300), (0, 0), 0, 1),
+                (Duration::from_millis(400), (0, 0), 0, 1),
+            ],
+            reporter.flush().1,
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fills_empty_windows_when_merging() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_empty_windows(EmptyWindows::BetweenEntries),
+        );
+        let (mut one, mut two) = (builder.build(), builder.build());
+
+        populate_test_metric(
+            &mut one,
+            vec![Action::Add(ReportMetric::One, Duration::from_millis(10))],
+        )
+        .await;
+
+        populate_test_metric(
+            &mut two,
+            vec![
+                Action::Wait(Duration::from_millis(300)),
+                Action::Add(ReportMetric::Two, Duration::from_millis(30)),
+            ],
+        )
+        .await;
+
+        let mut aggregated = builder.build();
+        one.merge_into(&mut aggregated);
+        two.merge_into(&mut aggregated);
+
+        verify_timeline(
+            vec![
+                (Duration::from_millis(0), (10, 0), 0, 2),
+                (Duration::from_millis(100), (0, 0), 0, 2),
+                (Duration::from_millis(200), (0, 0), 0, 2),
+                (Duration::from_millis(300), (0, 30), 0, 2),
+            ],
+            aggregated.flush().1,
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn downsamples_windows_outside_of_full_resolution() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_retention(TimelineRetention::downsample(2, 2).unwrap()),
+        );
+        let mut reporter = builder.build();
+
+        let mut actions = Vec::new();
+        for latency in 1..=7 {
+            actions.push(Action::Add(ReportMetric::One, Duration::from_millis(latency)));
+            actions.push(Action::Wait(Duration::from_millis(100)));
+        }
+        populate_test_metric(&mut reporter, actions).await;
+
+        let timeline = reporter.flush().1;
+        let windows: Vec<_> = timeline
+            .iter()
+            .map(|item| (*item.time(), *item.window(), item.total_count()))
+            .collect();
+
+        assert_eq!(
+            windows,
+            vec![
+                (Duration::from_millis(0), Duration::from_millis(200), 2),
+                (Duration::from_millis(200), Duration::from_millis(200), 2),
+                (Duration::from_millis(400), Duration::from_millis(100), 1),
+                (Duration::from_millis(500), Duration::from_millis(100), 1),
+                (Duration::from_millis(600), Duration::from_millis(100), 1),
+            ]
+        );
+        assert_eq!(timeline[1].min_value(ReportMetric::One), 3);
+        assert_eq!(timeline[1].max_value(ReportMetric::One), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cascades_downsampled_buckets_into_coarser_levels() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_retention(TimelineRetention::downsample(1, 2).unwrap()),
+        );
+        let mut reporter = builder.build();
+
+        let mut actions = Vec::new();
+        for latency in 1..=16 {
+            actions.push(Action::Add(ReportMetric::One, Duration::from_millis(latency)));
+            actions.push(Action::Wait(Duration::from_millis(100)));
+        }
+        populate_test_metric(&mut reporter, actions).await;
+
+        let windows: Vec<_> = reporter
+            .flush()
+            .1
+            .iter()
+            .map(|item| (*item.time(), *item.window(), item.total_count()))
+            .collect();
+
+        assert_eq!(
+            windows,
+            vec![
+                (Duration::from_millis(0), Duration::from_millis(800), 8),
+                (Duration::from_millis(800), Duration::from_millis(400), 4),
+                (Duration::from_millis(1200), Duration::from_millis(200), 2),
+                (Duration::from_millis(1400), Duration::from_millis(100), 1),
+                (Duration::from_millis(1500), Duration::from_millis(100), 1),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn downsamples_idle_tail_of_whole_run_on_flush() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_empty_windows(EmptyWindows::WholeRun)
+                .with_retention(TimelineRetention::downsample(2, 2).unwrap()),
+        );
+        let mut reporter = builder.build();
+
+        populate_test_metric(
+            &mut reporter,
+            vec![
+                Action::Add(ReportMetric::One, Duration::from_millis(10)),
+                Action::Wait(Duration::from_millis(700)),
+            ],
+        )
+        .await;
+
+        let windows: Vec<_> = reporter
+            .flush()
+            .1
+            .iter()
+            .map(|item| (*item.time(), *item.window(), item.total_count()))
+            .collect();
+
+        assert_eq!(
+            windows,
+            vec![
+                (Duration::from_millis(0), Duration::from_millis(200), 1),
+                (Duration::from_millis(200), Duration::from_millis(200), 0),
+                (Duration::from_millis(400), Duration::from_millis(200), 0),
+                (Duration::from_millis(600), Duration::from_millis(100), 0),
+                (Duration::from_millis(700), Duration::from_millis(100), 0),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn merges_fine_windows_into_downsampled_buckets() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_retention(TimelineRetention::downsample(2, 2).unwrap()),
+        );
+        let (mut downsampled, mut user) = (builder.build(), builder.build());
+
+        for latency in 1..=7 {
+            downsampled.add_entry(ReportMetric::One, Duration::from_millis(latency), None);
+            if latency == 2 {
+                user.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+            }
+            advance(Duration::from_millis(100)).await;
+        }
+
+        let mut aggregated = builder.build();
+        downsampled.merge_into(&mut aggregated);
+        user.merge_into(&mut aggregated);
+
+        let timeline = aggregated.flush().1;
+        let windows: Vec<_> = timeline
+            .iter()
+            .map(|item| (*item.time(), *item.window(), item.total_count()))
+            .collect();
+
+        assert_eq!(
+            windows,
+            vec![
+                (Duration::from_millis(0), Duration::from_millis(200), 3),
+                (Duration::from_millis(200), Duration::from_millis(200), 2),
+                (Duration::from_millis(400), Duration::from_millis(100), 1),
+                (Duration::from_millis(500), Duration::from_millis(100), 1),
+                (Duration::from_millis(600), Duration::from_millis(100), 1),
+            ]
+        );
+        assert_eq!(timeline[0].max_value(ReportMetric::One), 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drains_closed_windows_from_timeline() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds)
+                .with_empty_windows(EmptyWindows::WholeRun),
+        );
+        let mut reporter = builder.build();
+
+        populate_test_metric(
+            &mut reporter,
+            vec![
+                Action::Add(ReportMetric::One, Duration::from_millis(10)),
+                Action::Wait(Duration::from_millis(100)),
+                Action::Add(ReportMetric::One, Duration::from_millis(20)),
+                Action::Wait(Duration::from_millis(100)),
+                Action::Add(ReportMetric::Two, Duration::from_millis(30)),
+            ],
+        )
+        .await;
+
+        let drained = reporter.drain_closed();
+        let (total, timeline) = reporter.flush();
+
+        verify_timeline(
+            vec![
+                (Duration::from_millis(0), (10, 0), 0, 1),
+                (Duration::from_millis(100), (20, 0), 0, 1),
+            ],
+            drained,
+        );
+        verify_timeline(vec![(Duration::from_millis(200), (0, 30), 0, 1)], timeline);
+        assert_eq!(total.total_count(), 3);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn allows_splitting_chained_storage_in_timeline() {
         let builder = TimelineAggregateBuilder::with_settings(
@@ -389,6 +1043,58 @@ mod tests {
         assert_eq!(storage.storage().value(ReportMetric::Two).min(), 20000);
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn accounts_users_by_time_they_are_active_within_window() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds),
+        );
+        let (mut active, idle) = (builder.build(), builder.build());
+
+        advance(Duration::from_millis(100)).await;
+        drop(idle);
+        active.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        advance(Duration::from_millis(100)).await;
+        active.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        advance(Duration::from_millis(50)).await;
+
+        let (total, timeline) = active.flush();
+
+        assert_eq!((timeline[0].min_users(), timeline[0].users()), (1, 2));
+        assert!((timeline[0].mean_users() - 1.5).abs() < 1e-9);
+        assert_eq!((timeline[1].min_users(), timeline[1].users()), (1, 1));
+        assert_eq!((total.min_users(), total.users()), (1, 2));
+        assert!((total.mean_users() - 1.4).abs() < 1e-9);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_users_of_drained_windows_in_total() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds),
+        );
+        let (mut active, idle) = (builder.build(), builder.build());
+
+        advance(Duration::from_millis(100)).await;
+        drop(idle);
+        active.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        advance(Duration::from_millis(100)).await;
+        active.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        advance(Duration::from_millis(50)).await;
+
+        let drained = active.drain_closed();
+        let (total, timeline) = active.flush();
+
+        assert_eq!((drained.len(), timeline.len()), (2, 0));
+        assert_eq!((drained[0].min_users(), drained[0].users()), (1, 2));
+        assert_eq!((total.min_users(), total.users()), (1, 2));
+        assert!((total.mean_users() - 1.4).abs() < 1e-9);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn counts_errors_and_users_in_total() {
         let builder = TimelineAggregateBuilder::with_settings(
@@ -419,6 +1125,159 @@ mod tests {
         assert_eq!(total.errors(), 1);
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn calculates_throughput_for_timeline_and_total() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds),
+        );
+        let mut aggregate = builder.build();
+
+        populate_test_metric(
+            &mut aggregate,
+            vec![
+                Action::Add(ReportMetric::One, Duration::from_millis(10)),
+                Action::Add(ReportMetric::One, Duration::from_millis(10)),
+                Action::Error(
+                    ReportMetric::Two,
+                    Duration::from_millis(10),
+                    MetricRecordError::Timeout(Duration::from_millis(10)),
+                ),
+                Action::Wait(Duration::from_millis(300)),
+                Action::Add(ReportMetric::Two, Duration::from_millis(20)),
+            ],
+        )
+        .await;
+
+        let (total, timeline) = aggregate.flush();
+
+        assert_eq!(timeline[0].count(ReportMetric::One), 2);
+        assert_eq!(timeline[0].throughput(ReportMetric::One), 20.0);
+        assert_eq!(timeline[0].total_throughput(), 30.0);
+        assert_eq!(timeline[1].total_throughput(), 10.0);
+
+        assert_eq!(*total.window(), Duration::from_millis(400));
+        assert_eq!(total.total_count(), 4);
+        assert_eq!(total.count(ReportMetric::Two), 2);
+        assert_eq!(total.throughput(ReportMetric::Two), 5.0);
+        assert_eq!(total.total_throughput(), 10.0);
+        assert_eq!(total.error_rate(), 0.25);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn merges_total_values_of_aggregates() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds),
+        );
+        let (mut one, mut two) = (builder.build(), builder.build());
+
+        populate_test_metric(
+            &mut one,
+            vec![Action::Add(ReportMetric::One, Duration::from_millis(10))],
+        )
+        .await;
+
+        populate_test_metric(
+            &mut two,
+            vec![
+                Action::Wait(Duration::from_millis(100)),
+                Action::Add(ReportMetric::One, Duration::from_millis(30)),
+            ],
+        )
+        .await;
+
+        let mut aggregated = builder.build();
+        one.merge_into(&mut aggregated);
+        two.merge_into(&mut aggregated);
+
+        let total = aggregated.flush().0;
+
+        assert_eq!(total.count(ReportMetric::One), 2);
+        assert_eq!(total.max_value(ReportMetric::One), 30);
+        assert_eq!(*total.window(), Duration::from_millis(200));
+        assert_eq!(total.throughput(ReportMetric::One), 10.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resizes_limited_histograms_for_automatic_scale() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::with_limit(3, 60_000)
+                .unwrap()
+                .and(TotalAggregateStorage::with_limit(3, 60_000).unwrap()),
+            AggregateSettings::default().with_scale(AggregateScale::Auto),
+        );
+        let mut aggregate = builder.build();
+
+        aggregate.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+
+        let (total, _) = aggregate.flush();
+
+        assert_eq!(total.left().count(ReportMetric::One), 1);
+        assert_eq!(total.left().overflow_count(ReportMetric::One), 0);
+        assert_eq!(total.right().overflow_count(), 0);
+        assert_eq!(
+            total.left().readable_scale(ReportMetric::One, AggregateScale::Auto),
+            AggregateScale::Milliseconds
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_values_without_counting_operations() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default().and(TotalAggregateStorage::default()),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds),
+        );
+        let mut aggregate = builder.build();
+
+        aggregate.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        aggregate.add_value(ReportMetric::Two, MetricValue::Bytes(1500));
+        advance(Duration::from_millis(100)).await;
+        aggregate.add_value(ReportMetric::Two, MetricValue::Bytes(500));
+
+        let (total, timeline) = aggregate.flush();
+
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].total_count(), 1);
+        assert_eq!(timeline[0].left().distribution_max(ReportMetric::Two), 1500);
+        assert_eq!(timeline[0].left().count(ReportMetric::Two), 0);
+        assert_eq!(timeline[1].total_count(), 0);
+        assert_eq!(total.left().sum(ReportMetric::Two), 2000);
+        assert_eq!(total.left().sum_per_second(ReportMetric::Two), 10000.0);
+        assert_eq!(total.right().max_value(), 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn merges_relations_of_transactions() {
+        let builder =
+            TimelineAggregateBuilder::new(MetricAggregateStorage::<ReportMetric>::default());
+        let (mut one, mut two) = (builder.build(), builder.build());
+
+        one.add_relation("two", "one");
+        one.add_relation("two", "one");
+        two.add_relation("two", "one");
+        two.add_relation("one", "two");
+
+        one.merge_into(&mut two);
+
+        let mut relations: Vec<_> = two.relations().collect();
+        relations.sort();
+
+        assert_eq!(
+            relations,
+            vec![
+                ("one", "two"),
+                ("two", "one")
+            ]
+        );
+    }
+
     #[tokio::test(start_paused = true)]
     async fn reduces_users_count_on_removal_of_aggregators() {
         let builder = TimelineAggregateBuilder::with_settings(
@@ -0,0 +1,101 @@
+use crate::aggregate::{ApdexAggregateStorage, ApdexCounts};
+use crate::metric::Metric;
+
+use super::TimelineItem;
+
+impl<T> TimelineItem<ApdexAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    /// Satisfied, tolerating and frustrated operations for a metric
+    pub fn apdex(&self, metric: T) -> ApdexCounts {
+        self.as_view().apdex(metric)
+    }
+
+    /// Apdex score for a metric, empty when no operations were recorded
+    pub fn apdex_score(&self, metric: T) -> Option<f64> {
+        self.as_view().apdex_score(metric)
+    }
+}
+
+impl<T> TimelineItem<&ApdexAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    /// Satisfied, tolerating and frustrated operations for a metric
+    pub fn apdex(&self, metric: T) -> ApdexCounts {
+        self.storage().value(metric)
+    }
+
+    /// Apdex score for a metric, empty when no operations were recorded
+    pub fn apdex_score(&self, metric: T) -> Option<f64> {
+        self.apdex(metric).score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::aggregate::{AggregateStorage, ApdexThresholds, MetricAggregateStorage};
+    use crate::metric::MetricRecordError;
+
+    use super::*;
+
+    #[test]
+    fn reports_apdex_per_metric() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            ApdexAggregateStorage::new(ApdexThresholds::new(Duration::from_micros(100), Duration::from_micros(400))),
+            0,
+            1,
+        );
+
+        item.record("one", 50);
+        item.record("one", 300);
+        item.record("two", 500);
+
+        assert_eq!(item.apdex("one").satisfied(), 1);
+        assert_eq!(item.apdex_score("one"), Some(0.75));
+        assert_eq!(item.apdex_score("two"), Some(0.0));
+        assert_eq!(item.apdex_score("three"), None);
+    }
+
+    #[test]
+    fn reports_apdex_alongside_percentiles() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            MetricAggregateStorage::default()
+                .and(ApdexAggregateStorage::new(ApdexThresholds::new(Duration::from_micros(100), Duration::from_micros(400)))),
+            0,
+            1,
+        );
+
+        item.record("one", 50);
+        item.record("one", 1000);
+
+        assert_eq!(item.left().percentile_value("one", 99), 1000);
+        assert_eq!(item.right().apdex_score("one"), Some(0.5));
+    }
+
+    #[test]
+    fn reports_failed_operations_as_frustrated() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            MetricAggregateStorage::default().and(ApdexAggregateStorage::default()),
+            0,
+            1,
+        );
+        let error = MetricRecordError::Timeout(Duration::from_millis(10));
+
+        item.record_entry("one", 50, None);
+        item.record_entry("one", 50, Some(&error));
+
+        assert_eq!(item.left().count("one"), 2);
+        assert_eq!(item.right().apdex("one").frustrated(), 1);
+        assert_eq!(item.right().apdex_score("one"), Some(0.5));
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use crate::aggregate::{StartTime, TimelineRetention};
+
+use super::aggregate::{align_down, retention_levels};
+
+/// Number of changes sent by counters after which one of them folds them into the log
+const FOLD_EVERY: usize = 1024;
+
+/// Shared counter of active aggregates
+///
+/// Each clone is counted as an active user until it is dropped,
+/// start and stop of each user is integrated over time windows for time based accounting.
+/// Changes are sent over a lock-free channel and folded into the log when activity is read,
+/// or by a counter every [`FOLD_EVERY`] changes if the log is not locked at the moment,
+/// so concurrent clones and drops do not wait for each other.
+pub(crate) struct Counter {
+    shared: Arc<Shared>,
+    counted: bool,
+}
+
+struct Shared {
+    current: AtomicUsize,
+    zero: StartTime,
+    sent: AtomicUsize,
+    changes: Sender<(Duration, isize)>,
+    log: Mutex<Log>,
+}
+
+/// Activity log with changes that are not folded into it yet
+struct Log {
+    changes: Receiver<(Duration, isize)>,
+    activity: ActivityLog,
+}
+
+/// Number of active users or in-flight operations within a time span
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Activity {
+    pub(crate) min: usize,
+    pub(crate) max: usize,
+    pub(crate) mean: f64,
+}
+
+/// Level of a counter integrated over time windows as it changes
+///
+/// Windows are aligned in the same way as entries are assigned to the closest time window,
+/// so time span of each timeline item is covered by whole windows.
+/// Memory usage grows with the number of windows instead of the number of changes,
+/// and only logarithmically with the length of the run when timeline is downsampled.
+pub(crate) struct ActivityLog {
+    window: Duration,
+    retention: TimelineRetention,
+    level: isize,
+    time: Duration,
+    current: Span,
+    completed: VecDeque<Span>,
+    discarded: Span,
+}
+
+/// Level of a counter within a time window
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: Duration,
+    end: Duration,
+    range: Option<(isize, isize)>,
+    area: f64,
+}
+
+impl Counter {
+    pub(crate) fn new(zero: StartTime, window: Duration, retention: TimelineRetention) -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            shared: Arc::new(Shared {
+                current: AtomicUsize::new(0),
+                zero,
+                sent: AtomicUsize::new(0),
+                changes: sender,
+                log: Mutex::new(Log {
+                    changes: receiver,
+                    activity: ActivityLog::new(window, retention),
+                }),
+            }),
+            counted: false,
+        }
+    }
+
+    /// Counter sharing the same users without being counted as one of them
+    pub(crate) fn observer(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            counted: false,
+        }
+    }
+
+    fn change(&self, delta: isize) {
+        match delta {
+            1 => self.shared.current.fetch_add(1, Ordering::Relaxed),
+            _ => self.shared.current.fetch_sub(1, Ordering::Relaxed),
+        };
+
+        // Receiver is owned by the same shared state, so sending cannot fail
+        let _ = self.shared.changes.send((self.shared.zero.elapsed(), delta));
+
+        if self.shared.sent.fetch_add(1, Ordering::Relaxed) % FOLD_EVERY == FOLD_EVERY - 1 {
+            if let Ok(mut log) = self.shared.log.try_lock() {
+                log.fold();
+            }
+        }
+    }
+
+    pub(crate) fn current(&self) -> usize {
+        self.shared.current.load(Ordering::Relaxed)
+    }
+
+    /// Time weighted number of users within a time span since the zero point
+    ///
+    /// Span is limited by current time, so it is empty for spans that have not started yet
+    pub(crate) fn activity(&self, start: Duration, end: Duration) -> Option<Activity> {
+        self.activities([(start, end)]).pop().flatten()
+    }
+
+    /// Time weighted number of users within each of time spans ordered by their start
+    pub(crate) fn activities(
+        &self,
+        spans: impl IntoIterator<Item = (Duration, Duration)>,
+    ) -> Vec<Option<Activity>> {
+        let mut log = self.log();
+
+        log.activity.activities(self.shared.zero.elapsed(), spans)
+    }
+
+    /// Discards windows of users that end before provided time
+    ///
+    /// Spans starting at zero time, like the total of an aggregate, still include them.
+    pub(crate) fn discard_until(&self, time: Duration) {
+        self.log().activity.discard_until(time);
+    }
+
+    /// Log with all changes sent so far folded into it
+    fn log(&self) -> MutexGuard<'_, Log> {
+        let mut log = self.shared.log.lock().unwrap_or_else(PoisonError::into_inner);
+        log.fold();
+        log
+    }
+}
+
+impl Log {
+    /// Applies pending changes in time order
+    ///
+    /// Changes that arrive after later ones were already folded are applied
+    /// at the time of the latest folded change.
+    fn fold(&mut self) {
+        let mut changes: Vec<_> = self.changes.try_iter().collect();
+        changes.sort_by_key(|(time, _)| *time);
+
+        for (time, delta) in changes {
+            self.activity.change(time, delta);
+        }
+    }
+}
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        self.change(1);
+
+        Self {
+            shared: self.shared.clone(),
+            counted: true,
+        }
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        if self.counted {
+            self.change(-1)
+        }
+    }
+}
+
+impl ActivityLog {
+    pub(crate) fn new(window: Duration, retention: TimelineRetention) -> Self {
+        Self {
+            window,
+            retention,
+            level: 0,
+            time: Duration::ZERO,
+            current: Span::new(Duration::ZERO, boundary_after(Duration::ZERO, window)),
+            completed: VecDeque::new(),
+            discarded: Span::new(Duration::ZERO, Duration::ZERO),
+        }
+    }
+
+    /// Changes level of the counter at provided time, changes are expected in time order
+    ///
+    /// Level that is replaced at the same time it was reached is not reported as min or max
+    pub(crate) fn change(&mut self, time: Duration, delta: isize) {
+        self.advance(time);
+        self.level += delta;
+    }
+
+    /// Integrates current level till provided time
+    fn advance(&mut self, time: Duration) {
+        let completed = self.completed.len();
+
+        while self.time < time {
+            let until = time.min(self.current.end);
+            self.current.hold(self.level, until - self.time);
+            self.time = until;
+
+            if until == self.current.end {
+                let next = Span::new(until, boundary_after(until, self.window));
+                self.completed.push_back(std::mem::replace(&mut self.current, next));
+            }
+        }
+
+        if self.completed.len() > completed {
+            self.apply_retention();
+        }
+    }
+
+    /// Merges windows outside of full resolution range into buckets of downsampled timeline
+    ///
+    /// Buckets follow the same levels as timeline items, so each downsampled item is still
+    /// covered by whole windows, while finer spans within a bucket report none of them.
+    fn apply_retention(&mut self) {
+        let (full_resolution, factor) = match self.retention {
+            TimelineRetention::Downsample(downsample) if !self.window.is_zero() => {
+                (downsample.full_resolution(), downsample.factor())
+            }
+            _ => return,
+        };
+
+        let offset = self.window / 2;
+        let latest = self.current.end - offset;
+        let levels = retention_levels(latest, self.window, full_resolution, factor);
+        let bucket_of = |window: &Span| {
+            let time = window.end - offset;
+            match levels.iter().find(|(bound, _)| time >= *bound) {
+                Some((_, bucket)) if *bucket > self.window => align_down(time, *bucket),
+                _ => time,
+            }
+        };
+
+        let mut buckets: VecDeque<(Duration, Span)> = VecDeque::with_capacity(self.completed.len());
+        for window in std::mem::take(&mut self.completed) {
+            let bucket = bucket_of(&window);
+            match buckets.back_mut() {
+                Some((last, combined)) if *last == bucket => {
+                    *combined = combined.combine(&window);
+                    combined.end = window.end;
+                }
+                _ => buckets.push_back((bucket, window)),
+            }
+        }
+
+        self.completed = buckets.into_iter().map(|(_, window)| window).collect();
+    }
+
+    /// Min, max and mean level within each of time spans in a single pass over windows
+    ///
+    /// Level is integrated till provided current time that limits each of the spans.
+    /// Spans are expected to be ordered by their start, each one includes windows
+    /// that start within it.
+    pub(crate) fn activities(
+        &mut self,
+        now: Duration,
+        spans: impl IntoIterator<Item = (Duration, Duration)>,
+    ) -> Vec<Option<Activity>> {
+        self.advance(now);
+
+        let mut position = 0;
+        spans
+            .into_iter()
+            .map(|(start, end)| {
+                let end = end.min(now);
+                if start >= end {
+                    return None;
+                }
+
+                while self.completed.get(position).is_some_and(|window| window.start < start) {
+                    position += 1;
+                }
+
+                let within = |window: &&Span| window.start >= start && window.start < end;
+                let (combined, covered) = std::iter::once(&self.discarded)
+                    .filter(within)
+                    .chain(self.completed.range(position..).take_while(within))
+                    .chain(std::iter::once(&self.current).filter(within))
+                    .fold((Span::new(start, end), Duration::ZERO), |(combined, covered), window| {
+                        (combined.combine(window), covered + window.end.min(now) - window.start)
+                    });
+
+                combined.range.map(|(min, max)| Activity {
+                    min: min.max(0) as usize,
+                    max: max.max(0) as usize,
+                    mean: combined.area / covered.as_secs_f64(),
+                })
+            })
+            .collect()
+    }
+
+    /// Folds windows ending before provided time into a single window starting at zero
+    pub(crate) fn discard_until(&mut self, time: Duration) {
+        while let Some(window) = self.completed.front().filter(|window| window.end <= time) {
+            self.discarded = self.discarded.combine(window);
+            self.discarded.end = window.end;
+            self.completed.pop_front();
+        }
+    }
+}
+
+impl Span {
+    fn new(start: Duration, end: Duration) -> Self {
+        Self {
+            start,
+            end,
+            range: None,
+            area: 0.0,
+        }
+    }
+
+    fn hold(&mut self, level: isize, duration: Duration) {
+        if duration.is_zero() {
+            return;
+        }
+
+        self.range = Some(match self.range {
+            Some((min, max)) => (min.min(level), max.max(level)),
+            None => (level, level),
+        });
+        self.area += level as f64 * duration.as_secs_f64();
+    }
+
+    fn combine(mut self, other: &Span) -> Self {
+        self.range = match (self.range, other.range) {
+            (Some((min, max)), Some((other_min, other_max))) => {
+                Some((min.min(other_min), max.max(other_max)))
+            }
+            (range, other) => range.or(other),
+        };
+        self.area += other.area;
+        self
+    }
+}
+
+/// End of the time window that contains provided time
+///
+/// Boundaries of windows lie half of the window after each multiple of it
+fn boundary_after(time: Duration, window: Duration) -> Duration {
+    let (window, half) = (window.as_nanos(), window.as_nanos() / 2);
+    match time.as_nanos() {
+        _ if window == 0 => Duration::MAX,
+        time if time < half => Duration::from_nanos(half as u64),
+        time => Duration::from_nanos(((time - half) / window * window + window + half) as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::advance;
+
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(100);
+
+    fn millis(start: u64, end: u64) -> (Duration, Duration) {
+        (Duration::from_millis(start), Duration::from_millis(end))
+    }
+
+    fn assert_activity(activity: Option<Activity>, (min, max, mean): (usize, usize, f64)) {
+        let activity = activity.unwrap();
+
+        assert_eq!((activity.min, activity.max), (min, max));
+        assert!((activity.mean - mean).abs() < 1e-9, "{}", activity.mean);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn counts_active_users() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+        let observer = counter.observer();
+
+        let (one, two) = (counter.clone(), counter.clone());
+        assert_eq!(observer.current(), 2);
+
+        drop(one);
+        drop(two);
+        assert_eq!(observer.current(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn calculates_time_weighted_users_within_span() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+
+        let one = counter.clone();
+        advance(Duration::from_millis(25)).await;
+        let two = counter.clone();
+        advance(Duration::from_millis(50)).await;
+        drop(one);
+        advance(Duration::from_millis(75)).await;
+
+        let activities = counter.activities([millis(0, 50), millis(50, 150)]);
+
+        assert_activity(
+            counter.activity(Duration::ZERO, Duration::MAX),
+            (1, 2, 4.0 / 3.0),
+        );
+        assert_activity(activities[0], (1, 2, 1.5));
+        assert_activity(activities[1], (1, 2, 1.25));
+
+        drop(two);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ignores_users_replaced_at_the_same_time() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+
+        let one = counter.clone();
+        advance(Duration::from_millis(50)).await;
+        drop(one);
+        let _two = counter.clone();
+        advance(Duration::from_millis(50)).await;
+
+        let activity = counter.activity(Duration::ZERO, Duration::from_millis(100)).unwrap();
+
+        assert_eq!((activity.min, activity.max), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_no_activity_for_future_span() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+        let _one = counter.clone();
+
+        assert_eq!(
+            counter.activity(Duration::from_millis(150), Duration::from_millis(250)),
+            None
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_users_of_discarded_windows_in_total() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+
+        let one = counter.clone();
+        advance(Duration::from_millis(150)).await;
+        drop(one);
+        advance(Duration::from_millis(100)).await;
+
+        counter.discard_until(Duration::from_millis(150));
+
+        assert_eq!(
+            counter.activity(Duration::from_millis(50), Duration::from_millis(150)),
+            None
+        );
+        assert_activity(
+            counter.activity(Duration::from_millis(150), Duration::from_millis(250)),
+            (0, 0, 0.0),
+        );
+        assert_activity(counter.activity(Duration::ZERO, Duration::MAX), (0, 1, 0.6));
+    }
+
+    #[test]
+    fn folds_changes_of_users_from_multiple_threads() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+        let _user = counter.clone();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        drop(counter.clone());
+                    }
+                });
+            }
+        });
+
+        let activity = counter.activity(Duration::ZERO, Duration::MAX).unwrap();
+
+        assert_eq!(counter.current(), 1);
+        assert!((1..=5).contains(&activity.max), "{}", activity.max);
+        assert!(
+            activity.mean > 0.5 && activity.mean <= 5.0,
+            "{}",
+            activity.mean
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn folds_changes_into_log_without_reading_activity() {
+        let counter = Counter::new(StartTime::now(), WINDOW, TimelineRetention::default());
+
+        for _ in 0..FOLD_EVERY / 2 {
+            drop(counter.clone());
+        }
+
+        let log = counter.shared.log.lock().unwrap();
+        assert!(log.changes.try_recv().is_err());
+    }
+
+    #[test]
+    fn merges_windows_outside_of_full_resolution_into_buckets() {
+        let mut log = ActivityLog::new(
+            WINDOW,
+            TimelineRetention::downsample(2, 2).unwrap(),
+        );
+
+        log.change(Duration::ZERO, 1);
+        log.change(Duration::from_millis(400), -1);
+        log.change(Duration::from_millis(9950), 2);
+
+        let activities = log.activities(
+            Duration::from_millis(10050),
+            [millis(0, 10050), millis(9750, 9950), millis(9950, 10050)],
+        );
+
+        assert!(log.completed.len() < 20, "{}", log.completed.len());
+        assert_activity(activities[0], (0, 2, 0.6 / 10.05));
+        assert_activity(activities[1], (0, 0, 0.0));
+        assert_activity(activities[2], (2, 2, 2.0));
+    }
+
+    #[test]
+    fn aligns_windows_with_closest_time_window_of_entries() {
+        assert_eq!(
+            boundary_after(Duration::ZERO, WINDOW),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            boundary_after(Duration::from_millis(50), WINDOW),
+            Duration::from_millis(150)
+        );
+        assert_eq!(
+            boundary_after(Duration::from_millis(149), WINDOW),
+            Duration::from_millis(150)
+        );
+        assert_eq!(
+            boundary_after(Duration::from_secs(1), Duration::ZERO),
+            Duration::MAX
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use rustc_hash::FxHashMap;
+
+use crate::aggregate::{StartTime, TimelineRetention};
+use crate::metric::Metric;
+
+use super::counter::{Activity, Counter};
+
+/// Number of in-flight operations per metric shared between aggregates of a builder
+///
+/// Enabled via [`AggregateSettings::with_in_flight`](crate::aggregate::AggregateSettings::with_in_flight),
+/// measurers track each operation from its start till its completion or cancellation,
+/// so concurrency of a metric is integrated over time windows like active users.
+///
+/// Operations are counted per metric name, so tagged metrics that differ only by their tags
+/// share a single counter, the same way as concurrency is reported per metric name.
+#[derive(Clone)]
+pub struct InFlightTracker {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    zero: StartTime,
+    window: Duration,
+    retention: TimelineRetention,
+    metrics: RwLock<FxHashMap<Arc<str>, Counter>>,
+}
+
+/// In-flight operations of a single metric
+///
+/// Resolved once per metric by a measurer, so starting an operation
+/// does not look up the metric in the tracker
+pub(crate) struct InFlightMetric {
+    counter: Counter,
+}
+
+/// Operation in-flight, completed when dropped, including cancellation of its future
+pub(crate) struct InFlightOperation {
+    _counter: Counter,
+}
+
+impl InFlightTracker {
+    pub(crate) fn new(zero: StartTime, window: Duration, retention: TimelineRetention) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                zero,
+                window,
+                retention,
+                metrics: RwLock::default(),
+            }),
+        }
+    }
+
+    /// Number of currently in-flight operations of a metric
+    pub fn in_flight(&self, metric: impl Metric) -> usize {
+        let metrics = self.shared.metrics.read().unwrap_or_else(PoisonError::into_inner);
+
+        metrics.get(metric.name()).map_or(0, Counter::current)
+    }
+
+    /// In-flight operations of a metric, registered with the tracker on first use
+    pub(crate) fn metric(&self, metric: &impl Metric) -> InFlightMetric {
+        let metrics = self.shared.metrics.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(counter) = metrics.get(metric.name()) {
+            return InFlightMetric {
+                counter: counter.observer(),
+            };
+        }
+        drop(metrics);
+
+        let mut metrics = self.shared.metrics.write().unwrap_or_else(PoisonError::into_inner);
+        let counter = metrics.entry(metric.name().into()).or_insert_with(|| {
+            Counter::new(self.shared.zero, self.shared.window, self.shared.retention)
+        });
+
+        InFlightMetric {
+            counter: counter.observer(),
+        }
+    }
+
+    /// Time weighted number of in-flight operations of each metric within time spans
+    pub(crate) fn activities(
+        &self,
+        spans: &[(Duration, Duration)],
+    ) -> Vec<(Arc<str>, Vec<Option<Activity>>)> {
+        let metrics = self.shared.metrics.read().unwrap_or_else(PoisonError::into_inner);
+
+        metrics
+            .iter()
+            .map(|(metric, counter)| (metric.clone(), counter.activities(spans.iter().copied())))
+            .collect()
+    }
+
+    /// Discards windows of in-flight operations that end before provided time
+    pub(crate) fn discard_until(&self, time: Duration) {
+        let metrics = self.shared.metrics.read().unwrap_or_else(PoisonError::into_inner);
+
+        for counter in metrics.values() {
+            counter.discard_until(time);
+        }
+    }
+}
+
+impl InFlightMetric {
+    /// Starts an operation, it is in-flight until returned operation is dropped
+    pub(crate) fn start(&self) -> InFlightOperation {
+        InFlightOperation {
+            _counter: self.counter.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::advance;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn counts_in_flight_operations_per_metric() {
+        let tracker = InFlightTracker::new(
+            StartTime::now(),
+            Duration::from_millis(100),
+            TimelineRetention::default(),
+        );
+        let other = tracker.clone();
+
+        let first = tracker.metric(&"one").start();
+        let second = other.metric(&"one").start();
+        let _third = other.metric(&"two").start();
+
+        assert_eq!(tracker.in_flight("one"), 2);
+
+        drop(first);
+        drop(second);
+
+        assert_eq!(tracker.in_flight("one"), 0);
+        assert_eq!(tracker.in_flight("two"), 1);
+        assert_eq!(tracker.in_flight("three"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn integrates_in_flight_operations_over_time() {
+        let tracker = InFlightTracker::new(
+            StartTime::now(),
+            Duration::from_millis(100),
+            TimelineRetention::default(),
+        );
+
+        let long = tracker.metric(&"one").start();
+        advance(Duration::from_millis(50)).await;
+        let short = tracker.metric(&"one").start();
+        advance(Duration::from_millis(25)).await;
+        drop(short);
+        advance(Duration::from_millis(175)).await;
+
+        let activities = tracker.activities(&[
+            (Duration::ZERO, Duration::from_millis(50)),
+            (Duration::from_millis(50), Duration::from_millis(150)),
+            (Duration::from_millis(150), Duration::from_millis(250)),
+        ]);
+        drop(long);
+
+        let (metric, activities) = &activities[0];
+        let activities: Vec<_> = activities
+            .iter()
+            .map(|activity| activity.map(|activity| (activity.max, activity.mean)))
+            .collect();
+
+        assert_eq!(metric.as_ref(), "one");
+        assert_eq!(
+            activities,
+            vec![Some((1, 1.0)), Some((2, 1.25)), Some((1, 1.0))]
+        );
+    }
+}
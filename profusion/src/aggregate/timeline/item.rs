@@ -4,18 +4,29 @@
  * See LICENSE for license details.
  */
 
-use std::cmp::max;
+use std::cmp::{max, min};
+use std::sync::Arc;
 use std::time::Duration;
 
+use hdrhistogram::Histogram;
+use rustc_hash::FxHashMap;
+
 use crate::aggregate::{AggregateStorage, CombinedAggregateStorage};
-use crate::metric::MetricRecordError;
+
+use super::counter::Activity;
+use crate::metric::{Metric, MetricRecordError, MetricValue};
 
 #[derive(Debug)]
 pub struct TimelineItem<S> {
     time: Duration,
+    window: Duration,
     storage: S,
     errors: usize,
     users: usize,
+    min_users: usize,
+    mean_users: f64,
+    operations: usize,
+    concurrency: Arc<FxHashMap<Arc<str>, Activity>>,
 }
 
 impl<S> Eq for TimelineItem<S> {}
@@ -42,19 +53,40 @@ impl<S> TimelineItem<S>
 where
     S: AggregateStorage,
 {
-    pub(crate) fn new(time: Duration, storage: S, errors: usize, users: usize) -> Self {
+    pub(crate) fn new(
+        time: Duration,
+        window: Duration,
+        storage: S,
+        errors: usize,
+        users: usize,
+    ) -> Self {
         Self {
             time,
+            window,
             storage,
             errors,
             users,
+            min_users: users,
+            mean_users: users as f64,
+            operations: 0,
+            concurrency: Arc::default(),
         }
     }
+}
 
+impl<S> TimelineItem<S> {
     pub fn time(&self) -> &Duration {
         &self.time
     }
 
+    /// Time span covered by the item
+    ///
+    /// Equals to [`AggregateSettings::window`](crate::aggregate::AggregateSettings::window)
+    /// for timeline items and to the whole recorded period for the total item
+    pub fn window(&self) -> &Duration {
+        &self.window
+    }
+
     pub(crate) fn storage(&self) -> &S {
         &self.storage
     }
@@ -63,14 +95,105 @@ where
         self.errors
     }
 
+    /// Highest number of active users within the item
     pub fn users(&self) -> usize {
         self.users
     }
 
+    /// Lowest number of active users within the item
+    pub fn min_users(&self) -> usize {
+        self.min_users
+    }
+
+    /// Time weighted average number of active users within the item
+    pub fn mean_users(&self) -> f64 {
+        self.mean_users
+    }
+
+    /// Highest number of in-flight operations of a metric within the item
+    ///
+    /// Reported only when [`AggregateSettings::with_in_flight`](crate::aggregate::AggregateSettings::with_in_flight)
+    /// is enabled, operations of the whole stream are in-flight for measured streams
+    pub fn peak_concurrency(&self, metric: impl Metric) -> usize {
+        self.concurrency
+            .get(metric.name())
+            .map(|activity| activity.max)
+            .unwrap_or_default()
+    }
+
+    /// Time weighted average number of in-flight operations of a metric within the item
+    pub fn mean_concurrency(&self, metric: impl Metric) -> f64 {
+        self.concurrency
+            .get(metric.name())
+            .map(|activity| activity.mean)
+            .unwrap_or_default()
+    }
+
+    /// Number of completed operations within the item
+    pub fn total_count(&self) -> usize {
+        self.operations
+    }
+
+    /// Completed operations per second within the item
+    pub fn total_throughput(&self) -> f64 {
+        per_second(self.operations as u64, &self.window)
+    }
+
+    /// Ratio of failed operations to completed ones within the item
+    pub fn error_rate(&self) -> f64 {
+        match self.operations {
+            0 => 0.0,
+            operations => self.errors as f64 / operations as f64,
+        }
+    }
+
+    /// Borrowed view of the item
+    ///
+    /// Exposes the same accessors as the item itself
+    pub fn as_view(&self) -> TimelineItem<&S> {
+        self.with_storage(&self.storage)
+    }
+
+    pub(super) fn with_storage<V>(&self, storage: V) -> TimelineItem<V> {
+        TimelineItem {
+            time: self.time,
+            window: self.window,
+            storage,
+            errors: self.errors,
+            users: self.users,
+            min_users: self.min_users,
+            mean_users: self.mean_users,
+            operations: self.operations,
+            concurrency: self.concurrency.clone(),
+        }
+    }
+}
+
+impl<S> TimelineItem<S>
+where
+    S: AggregateStorage,
+{
     pub(crate) fn record(&mut self, metric: S::Metric, value: u64) {
         self.storage.record(metric, value)
     }
 
+    /// Records latency of an operation, as a failure when it has an error
+    pub(crate) fn record_entry(
+        &mut self,
+        metric: S::Metric,
+        value: u64,
+        error: Option<&MetricRecordError>,
+    ) {
+        match error {
+            Some(_) => self.storage.record_failure(metric, value),
+            None => self.record(metric, value),
+        }
+    }
+
+    pub(crate) fn record_value(&mut self, metric: S::Metric, value: MetricValue) {
+        self.storage.record_value(metric, value)
+    }
+
     pub(crate) fn update_counters(
         &mut self,
         error: Option<&MetricRecordError>,
@@ -80,14 +203,68 @@ where
             self.errors += 1;
         }
 
-        self.users = users
+        self.operations += 1;
+        self.users = users;
+        self.min_users = users;
+        self.mean_users = users as f64;
+    }
+
+    /// Replaces users observed on entries with users active within the time span of the item
+    pub(crate) fn update_users(&mut self, activity: Activity) {
+        self.users = activity.max;
+        self.min_users = activity.min;
+        self.mean_users = activity.mean;
+    }
+
+    /// Replaces in-flight operations of a metric with ones within the time span of the item
+    pub(crate) fn update_concurrency(&mut self, metric: Arc<str>, activity: Activity) {
+        Arc::make_mut(&mut self.concurrency).insert(metric, activity);
+    }
+
+    /// Moves the item into a different time span
+    pub(crate) fn realign(&mut self, time: Duration, window: Duration) {
+        self.time = time;
+        self.window = window;
+    }
+
+    /// Extends time span of the item up to the end of provided time window
+    pub(crate) fn extend_until(&mut self, end: Duration) {
+        self.window = max(self.window, end.saturating_sub(self.time));
     }
 
     pub(crate) fn merge_into(self, other: &mut Self) {
+        let end = max(self.time + self.window, other.time + other.window);
         let storage = std::mem::take(&mut other.storage);
         other.storage = storage.merge(self.storage);
+        other.mean_users = weighted_mean(
+            (self.mean_users, &self.window),
+            (other.mean_users, &other.window),
+        );
+
+        if !self.concurrency.is_empty() {
+            let concurrency = Arc::make_mut(&mut other.concurrency);
+            for (metric, activity) in self.concurrency.iter() {
+                let merged = match concurrency.get(metric) {
+                    Some(existing) => Activity {
+                        min: min(existing.min, activity.min),
+                        max: max(existing.max, activity.max),
+                        mean: weighted_mean(
+                            (activity.mean, &self.window),
+                            (existing.mean, &other.window),
+                        ),
+                    },
+                    None => *activity,
+                };
+                concurrency.insert(metric.clone(), merged);
+            }
+        }
+
         other.users = max(other.users, self.users);
+        other.min_users = min(other.min_users, self.min_users);
         other.errors += self.errors;
+        other.operations += self.operations;
+        other.time = min(other.time, self.time);
+        other.window = end - other.time;
     }
 }
 
@@ -97,22 +274,76 @@ where
     R: AggregateStorage<Metric = L::Metric>,
 {
     pub fn split(self) -> (TimelineItem<L>, TimelineItem<R>) {
+        let counters = self.with_storage(());
         let (left_storage, right_storage) = self.storage.unwrap();
+
         (
-            TimelineItem {
-                time: self.time,
-                storage: left_storage,
-                errors: self.errors,
-                users: self.users,
-            },
-            TimelineItem {
-                storage: right_storage,
-                time: self.time,
-                errors: self.errors,
-                users: self.users,
-            },
+            counters.with_storage(left_storage),
+            counters.with_storage(right_storage),
         )
     }
+
+    /// View of the item with the first storage of the combination
+    ///
+    /// Nested combinations are accessible by chaining views,
+    /// e.g. `item.left().right()` for the second storage in `a.and(b).and(c)`
+    pub fn left(&self) -> TimelineItem<&L> {
+        self.with_storage(self.storage.left())
+    }
+
+    /// View of the item with the second storage of the combination
+    pub fn right(&self) -> TimelineItem<&R> {
+        self.with_storage(self.storage.right())
+    }
+}
+
+impl<'a, L, R> TimelineItem<&'a CombinedAggregateStorage<L, R>>
+where
+    L: AggregateStorage,
+    R: AggregateStorage<Metric = L::Metric>,
+{
+    /// View of the item with the first storage of the combination
+    pub fn left(&self) -> TimelineItem<&'a L> {
+        self.with_storage(self.storage.left())
+    }
+
+    /// View of the item with the second storage of the combination
+    pub fn right(&self) -> TimelineItem<&'a R> {
+        self.with_storage(self.storage.right())
+    }
+}
+
+/// Average of two values weighted by time spans they cover
+fn weighted_mean((one, one_span): (f64, &Duration), (two, two_span): (f64, &Duration)) -> f64 {
+    match (*one_span + *two_span).as_secs_f64() {
+        total if total > 0.0 => {
+            (one * one_span.as_secs_f64() + two * two_span.as_secs_f64()) / total
+        }
+        _ => one.max(two),
+    }
+}
+
+/// Converts number of operations into rate per second for a time span
+pub(crate) fn per_second(count: impl Into<u128>, window: &Duration) -> f64 {
+    match window.as_secs_f64() {
+        seconds if seconds > 0.0 => count.into() as f64 / seconds,
+        _ => 0.0,
+    }
+}
+
+/// Logarithmic distribution of histogram values with power of two buckets
+pub(crate) fn log_histogram(histogram: &Histogram<u64>) -> Vec<(u64, f64, u64)> {
+    let total_counts = histogram.len();
+    histogram
+        .iter_log(1, 2.0)
+        .map(|value| {
+            (
+                value.value_iterated_to() + 1,
+                (value.count_since_last_iteration() as f64 / total_counts as f64) * 100.0,
+                value.count_since_last_iteration(),
+            )
+        })
+        .collect::<Vec<_>>()
 }
 
 #[cfg(test)]
@@ -124,6 +355,7 @@ mod tests {
     #[test]
     fn splits_into_multiple_storages() {
         let mut item = TimelineItem::new(
+            Duration::from_millis(10),
             Duration::from_millis(10),
             MetricAggregateStorage::default().and(TotalAggregateStorage::default()),
             0,
@@ -137,4 +369,98 @@ mod tests {
         assert_eq!(left.storage().value("one").max(), 100);
         assert_eq!(right.storage().value().max(), 100);
     }
+
+    #[test]
+    fn provides_views_of_combined_storages_without_splitting() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(100),
+            MetricAggregateStorage::default()
+                .and(TotalAggregateStorage::default())
+                .and(MetricAggregateStorage::with_sigfig(1).unwrap()),
+            0,
+            1,
+        );
+
+        item.record("one", 100);
+        item.record("two", 1000);
+        item.update_counters(None, 1);
+        item.update_counters(None, 1);
+
+        assert_eq!(item.left().left().max_value("one"), 100);
+        assert_eq!(item.left().right().max_value(), 1000);
+        assert_eq!(item.right().max_value("two"), 1023);
+        assert_eq!(item.left().right().throughput(), 20.0);
+        assert_eq!(item.right().total_count(), 2);
+    }
+
+    #[test]
+    fn calculates_throughput_and_error_rate_per_window() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            TotalAggregateStorage::default(),
+            0,
+            1,
+        );
+
+        for error in [
+            None,
+            Some(MetricRecordError::Timeout(Duration::from_millis(10))),
+            None,
+            None,
+        ] {
+            item.record("one", 100);
+            item.update_counters(error.as_ref(), 1);
+        }
+
+        assert_eq!(item.total_count(), 4);
+        assert_eq!(item.total_throughput(), 20.0);
+        assert_eq!(item.error_rate(), 0.25);
+    }
+
+    #[test]
+    fn reports_zero_rates_for_empty_item() {
+        let item = TimelineItem::new(
+            Duration::from_millis(100),
+            Duration::ZERO,
+            TotalAggregateStorage::<&str>::default(),
+            0,
+            1,
+        );
+
+        assert_eq!(item.total_count(), 0);
+        assert_eq!(item.total_throughput(), 0.0);
+        assert_eq!(item.error_rate(), 0.0);
+    }
+
+    #[test]
+    fn merges_time_span_and_counters() {
+        let mut one = TimelineItem::new(
+            Duration::from_millis(100),
+            Duration::from_millis(100),
+            TotalAggregateStorage::default(),
+            0,
+            1,
+        );
+        let mut two = TimelineItem::new(
+            Duration::from_millis(300),
+            Duration::from_millis(100),
+            TotalAggregateStorage::default(),
+            0,
+            2,
+        );
+
+        one.record("one", 100);
+        one.update_counters(None, 1);
+        two.record("one", 100);
+        two.update_counters(None, 2);
+
+        one.merge_into(&mut two);
+
+        assert_eq!(*two.time(), Duration::from_millis(100));
+        assert_eq!(*two.window(), Duration::from_millis(300));
+        assert_eq!(two.total_count(), 2);
+        assert_eq!(two.users(), 2);
+    }
 }
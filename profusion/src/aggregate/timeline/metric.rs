@@ -1,9 +1,126 @@
-use crate::aggregate::MetricAggregateStorage;
-use crate::metric::Metric;
+use crate::aggregate::{AggregateScale, GaugeValue, MetricAggregateStorage};
+use crate::metric::{is_nested_metric, Metric, MetricTree};
 
-use super::TimelineItem;
+use super::{
+    item::{log_histogram, per_second},
+    TimelineItem,
+};
 
 impl<T> TimelineItem<MetricAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    pub fn min_value(&self, metric: T) -> u64 {
+        self.as_view().min_value(metric)
+    }
+
+    pub fn max_value(&self, metric: T) -> u64 {
+        self.as_view().max_value(metric)
+    }
+
+    pub fn mean_value(&self, metric: T) -> f64 {
+        self.as_view().mean_value(metric)
+    }
+
+    /// Number of recorded operations for a metric
+    pub fn count(&self, metric: T) -> u64 {
+        self.as_view().count(metric)
+    }
+
+    /// Recorded operations per second for a metric
+    pub fn throughput(&self, metric: T) -> f64 {
+        self.as_view().throughput(metric)
+    }
+
+    /// Number of distribution or bytes values recorded for a metric
+    pub fn distribution_count(&self, metric: T) -> u64 {
+        self.as_view().distribution_count(metric)
+    }
+
+    pub fn distribution_min(&self, metric: T) -> u64 {
+        self.as_view().distribution_min(metric)
+    }
+
+    pub fn distribution_max(&self, metric: T) -> u64 {
+        self.as_view().distribution_max(metric)
+    }
+
+    pub fn distribution_mean(&self, metric: T) -> f64 {
+        self.as_view().distribution_mean(metric)
+    }
+
+    pub fn distribution_percentile<P: Into<f64>>(&self, metric: T, percentile: P) -> u64 {
+        self.as_view().distribution_percentile(metric, percentile)
+    }
+
+    /// Sum of distribution or bytes values for a metric, e.g. total transferred bytes
+    pub fn sum(&self, metric: T) -> u128 {
+        self.as_view().sum(metric)
+    }
+
+    /// Sum of distribution or bytes values per second for a metric, e.g. transfer throughput
+    pub fn sum_per_second(&self, metric: T) -> f64 {
+        self.as_view().sum_per_second(metric)
+    }
+
+    /// Sum of counter increments for a metric
+    pub fn counter(&self, metric: T) -> u64 {
+        self.as_view().counter(metric)
+    }
+
+    /// Counter increments per second for a metric
+    pub fn counter_per_second(&self, metric: T) -> f64 {
+        self.as_view().counter_per_second(metric)
+    }
+
+    /// Aggregated gauge samples for a metric, empty when gauge was not recorded
+    pub fn gauge(&self, metric: T) -> Option<GaugeValue> {
+        self.as_view().gauge(metric)
+    }
+
+    /// Number of values above the limit of the histogram, see [`OverflowPolicy`](crate::aggregate::OverflowPolicy)
+    pub fn overflow_count(&self, metric: T) -> u64 {
+        self.as_view().overflow_count(metric)
+    }
+
+    pub fn percentile_value<P: Into<f64>>(&self, metric: T, percentile: P) -> u64 {
+        self.as_view().percentile_value(metric, percentile)
+    }
+
+    pub fn histogram(&self, metric: T) -> Vec<(u64, f64, u64)> {
+        self.as_view().histogram(metric)
+    }
+
+    /// Readable unit for all latencies of a metric recorded in provided scale
+    pub fn readable_scale(&self, metric: T, scale: AggregateScale) -> AggregateScale {
+        self.as_view().readable_scale(metric, scale)
+    }
+
+    /// Item with values of metrics aggregated under a new key, e.g. across tag values
+    ///
+    /// Metrics for which `key` returns `None` are left out
+    pub fn rollup<K, F>(&self, key: F) -> TimelineItem<MetricAggregateStorage<K>>
+    where
+        K: Metric + Send,
+        F: Fn(&T) -> Option<K>,
+    {
+        self.as_view().rollup(key)
+    }
+
+    /// Item with values of all metrics nested under the path aggregated under it
+    ///
+    /// E.g. `load_test_one` combines `load_test_one::one` and `load_test_one::two`
+    pub fn rollup_path<'a>(&self, path: &'a str) -> TimelineItem<MetricAggregateStorage<&'a str>> {
+        self.as_view().rollup_path(path)
+    }
+
+    /// Hierarchy of recorded metric names
+    pub fn metric_tree(&self) -> MetricTree {
+        self.as_view().metric_tree()
+    }
+}
+
+impl<T> TimelineItem<&MetricAggregateStorage<T>>
 where
     T: Metric + Send,
 {
@@ -19,23 +136,108 @@ where
         self.storage().value(metric).mean()
     }
 
+    /// Number of recorded operations for a metric
+    ///
+    /// Includes operations with latencies dropped above the limit of the histogram
+    pub fn count(&self, metric: T) -> u64 {
+        self.storage().count(metric)
+    }
+
+    /// Recorded operations per second for a metric
+    pub fn throughput(&self, metric: T) -> f64 {
+        per_second(self.count(metric), self.window())
+    }
+
+    /// Number of distribution or bytes values recorded for a metric
+    pub fn distribution_count(&self, metric: T) -> u64 {
+        self.storage().distribution(metric).len()
+    }
+
+    pub fn distribution_min(&self, metric: T) -> u64 {
+        self.storage().distribution(metric).min()
+    }
+
+    pub fn distribution_max(&self, metric: T) -> u64 {
+        self.storage().distribution(metric).max()
+    }
+
+    pub fn distribution_mean(&self, metric: T) -> f64 {
+        self.storage().distribution(metric).mean()
+    }
+
+    pub fn distribution_percentile<P: Into<f64>>(&self, metric: T, percentile: P) -> u64 {
+        self.storage().distribution(metric).value_at_percentile(percentile.into())
+    }
+
+    /// Sum of distribution or bytes values for a metric, e.g. total transferred bytes
+    ///
+    /// Sum is exact regardless of significant figures of the histogram
+    pub fn sum(&self, metric: T) -> u128 {
+        self.storage().sum(metric)
+    }
+
+    /// Sum of distribution or bytes values per second for a metric, e.g. transfer throughput
+    pub fn sum_per_second(&self, metric: T) -> f64 {
+        per_second(self.sum(metric), self.window())
+    }
+
+    /// Sum of counter increments for a metric
+    pub fn counter(&self, metric: T) -> u64 {
+        self.storage().counter(metric)
+    }
+
+    /// Counter increments per second for a metric
+    pub fn counter_per_second(&self, metric: T) -> f64 {
+        per_second(self.counter(metric), self.window())
+    }
+
+    /// Aggregated gauge samples for a metric, empty when gauge was not recorded
+    pub fn gauge(&self, metric: T) -> Option<GaugeValue> {
+        self.storage().gauge(metric)
+    }
+
+    /// Number of values above the limit of the histogram, see [`OverflowPolicy`](crate::aggregate::OverflowPolicy)
+    pub fn overflow_count(&self, metric: T) -> u64 {
+        self.storage().overflows(metric)
+    }
+
     pub fn percentile_value<P: Into<f64>>(&self, metric: T, percentile: P) -> u64 {
         self.storage().value(metric).value_at_percentile(percentile.into())
     }
 
     pub fn histogram(&self, metric: T) -> Vec<(u64, f64, u64)> {
-        let histogram = self.storage().value(metric);
-        let total_counts = histogram.len();
-        histogram
-            .iter_log(1, 2.0)
-            .map(|value| {
-                (
-                    value.value_iterated_to() + 1,
-                    (value.count_since_last_iteration() as f64 / total_counts as f64) * 100.0,
-                    value.count_since_last_iteration(),
-                )
-            })
-            .collect::<Vec<_>>()
+        log_histogram(self.storage().value(metric))
+    }
+
+    /// Readable unit for all latencies of a metric recorded in provided scale
+    ///
+    /// Unit is picked by the median latency, so values of a metric are formatted alike
+    /// via [`AggregateScale::format_value`] regardless of outliers
+    pub fn readable_scale(&self, metric: T, scale: AggregateScale) -> AggregateScale {
+        AggregateScale::readable(scale.value_to_duration(self.percentile_value(metric, 50)))
+    }
+
+    /// Item with values of metrics aggregated under a new key, e.g. across tag values
+    ///
+    /// Metrics for which `key` returns `None` are left out
+    pub fn rollup<K, F>(&self, key: F) -> TimelineItem<MetricAggregateStorage<K>>
+    where
+        K: Metric + Send,
+        F: Fn(&T) -> Option<K>,
+    {
+        self.with_storage(self.storage().rollup(key))
+    }
+
+    /// Item with values of all metrics nested under the path aggregated under it
+    ///
+    /// E.g. `load_test_one` combines `load_test_one::one` and `load_test_one::two`
+    pub fn rollup_path<'a>(&self, path: &'a str) -> TimelineItem<MetricAggregateStorage<&'a str>> {
+        self.rollup(|metric| is_nested_metric(metric.name(), path).then_some(path))
+    }
+
+    /// Hierarchy of recorded metric names
+    pub fn metric_tree(&self) -> MetricTree {
+        MetricTree::from_names(self.storage().metric_names())
     }
 }
 
@@ -43,11 +245,14 @@ where
 mod tests {
     use std::time::Duration;
 
+    use crate::metric::{MetricValue, TaggedMetric};
+
     use super::*;
 
     fn populate_timeline_item() -> TimelineItem<MetricAggregateStorage<&'static str>> {
         let mut item = TimelineItem::new(
             Duration::from_millis(10),
+            Duration::from_millis(500),
             MetricAggregateStorage::default(),
             0,
             1,
@@ -90,6 +295,184 @@ mod tests {
         assert_eq!(item.mean_value("two"), 850.0);
     }
 
+    #[test]
+    fn counts_operations_per_metric() {
+        let item = populate_timeline_item();
+        assert_eq!(item.count("one"), 10);
+        assert_eq!(item.count("two"), 2);
+        assert_eq!(item.count("three"), 0);
+    }
+
+    #[test]
+    fn calculates_throughput_per_metric() {
+        let item = populate_timeline_item();
+        assert_eq!(item.throughput("one"), 20.0);
+        assert_eq!(item.throughput("two"), 4.0);
+    }
+
+    #[test]
+    fn sums_values_per_metric() {
+        let mut item = populate_timeline_item();
+
+        item.record_value("bytes", MetricValue::Bytes(100));
+        item.record_value("bytes", MetricValue::Bytes(1600));
+        item.record_value("bytes", MetricValue::Bytes(123_457));
+
+        assert_eq!(item.sum("bytes"), 125_157);
+        assert_eq!(item.sum_per_second("bytes"), 250_314.0);
+        assert_eq!(item.sum("two"), 0);
+    }
+
+    #[test]
+    fn reports_distributions_apart_from_operations() {
+        let mut item = populate_timeline_item();
+
+        item.record_value("two", MetricValue::Distribution(1));
+        item.record_value("items", MetricValue::Distribution(2));
+        item.record_value("items", MetricValue::Distribution(10));
+
+        assert_eq!(item.count("two"), 2);
+        assert_eq!(item.min_value("two"), 100);
+        assert_eq!(item.percentile_value("two", 50), 100);
+        assert_eq!(item.distribution_count("two"), 1);
+        assert_eq!(item.distribution_count("items"), 2);
+        assert_eq!(item.distribution_min("items"), 2);
+        assert_eq!(item.distribution_max("items"), 10);
+        assert_eq!(item.distribution_mean("items"), 6.0);
+        assert_eq!(item.distribution_percentile("items", 50), 2);
+        assert_eq!(item.count("items"), 0);
+        assert_eq!(item.throughput("items"), 0.0);
+    }
+
+    #[test]
+    fn reports_counters_and_gauges_per_metric() {
+        let mut item = populate_timeline_item();
+
+        item.record_value("hits", MetricValue::Counter(40));
+        item.record_value("hits", MetricValue::Counter(60));
+        item.record_value("queue", MetricValue::Gauge(3));
+        item.record_value("queue", MetricValue::Gauge(7));
+
+        assert_eq!(item.counter("hits"), 100);
+        assert_eq!(item.counter_per_second("hits"), 200.0);
+        assert_eq!(item.gauge("queue").map(|gauge| gauge.max()), Some(7));
+        assert_eq!(item.gauge("hits"), None);
+        assert_eq!(item.count("hits"), 0);
+    }
+
+    #[test]
+    fn aggregates_across_tag_values() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            MetricAggregateStorage::default(),
+            0,
+            1,
+        );
+        let request = TaggedMetric::new("http_request");
+
+        item.record(request.with_tag("status", "2xx"), 100);
+        item.record(request.with_tag("status", "2xx"), 200);
+        item.record(request.with_tag("status", "5xx"), 1500);
+        item.record(TaggedMetric::new("db_query"), 50);
+
+        let all_statuses =
+            item.rollup(|metric| (metric.name() == "http_request").then_some(request));
+        let server_errors =
+            item.rollup(|metric| metric.has_tag("status", "5xx").then_some(request));
+
+        assert_eq!(all_statuses.count(request), 3);
+        assert_eq!(all_statuses.percentile_value(request, 99.0), 1500);
+        assert_eq!(all_statuses.count(TaggedMetric::new("db_query")), 0);
+        assert_eq!(server_errors.count(request), 1);
+        assert_eq!(server_errors.min_value(request), 1500);
+        assert_eq!(item.count(request.with_tag("status", "2xx")), 2);
+    }
+
+    #[test]
+    fn rolls_up_nested_metrics_by_path() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            MetricAggregateStorage::default(),
+            0,
+            1,
+        );
+
+        item.record("load_test_one::one", 100);
+        item.record("load_test_one::two", 300);
+        item.record("load_test_one::two", 500);
+        item.record("load_test_one_more", 1000);
+
+        let rollup = item.rollup_path("load_test_one");
+
+        assert_eq!(rollup.count("load_test_one"), 3);
+        assert_eq!(rollup.min_value("load_test_one"), 100);
+        assert_eq!(rollup.max_value("load_test_one"), 500);
+        assert_eq!(item.count("load_test_one::two"), 2);
+    }
+
+    #[test]
+    fn builds_tree_of_recorded_metrics() {
+        let item = populate_timeline_item();
+
+        let tree = item.metric_tree();
+
+        let paths: Vec<_> = tree.children().iter().map(MetricTree::path).collect();
+        assert_eq!(paths, ["one", "two"]);
+    }
+
+    #[test]
+    fn reports_overflowing_values_per_metric() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            MetricAggregateStorage::with_limit(3, 1000).unwrap(),
+            0,
+            1,
+        );
+
+        item.record("one", 500);
+        item.record("one", 100_000);
+
+        assert_eq!(item.overflow_count("one"), 1);
+        assert_eq!(item.overflow_count("two"), 0);
+        assert_eq!(item.count("one"), 2);
+        assert_eq!(item.throughput("one"), 4.0);
+    }
+
+    #[test]
+    fn provides_same_values_through_view() {
+        let item = populate_timeline_item();
+        let view = item.as_view();
+
+        assert_eq!(view.min_value("one"), item.min_value("one"));
+        assert_eq!(view.max_value("one"), item.max_value("one"));
+        assert_eq!(view.count("two"), item.count("two"));
+        assert_eq!(view.percentile_value("one", 90), item.percentile_value("one", 90));
+        assert_eq!(view.histogram("two"), item.histogram("two"));
+    }
+
+    #[test]
+    fn picks_readable_scale_per_metric() {
+        let item = populate_timeline_item();
+        let scale = item.readable_scale("one", AggregateScale::Microseconds);
+
+        assert_eq!(scale, AggregateScale::Milliseconds);
+        assert_eq!(
+            AggregateScale::Microseconds.format_value(item.min_value("one") as f64, scale),
+            "0.05ms"
+        );
+        assert_eq!(
+            item.readable_scale("two", AggregateScale::Microseconds),
+            AggregateScale::Microseconds
+        );
+        assert_eq!(
+            item.readable_scale("one", AggregateScale::Auto),
+            AggregateScale::Microseconds
+        );
+    }
+
     #[test]
     fn calculates_percentiles_per_metric() {
         let item = populate_timeline_item();
@@ -1,8 +1,16 @@
 pub use aggregate::*;
+pub use in_flight::*;
 pub use item::*;
+pub use sharded::*;
 
 mod item;
 mod metric;
 
 mod aggregate;
+mod apdex;
+mod counter;
+mod in_flight;
+mod sharded;
+mod sketch;
+mod summary;
 mod total;
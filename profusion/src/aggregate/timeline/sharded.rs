@@ -0,0 +1,375 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::metric::{MetricRecordError, MetricValue};
+use crate::prelude::*;
+
+type Shards<S> = Arc<[Mutex<TimelineAggregate<S>>]>;
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Index of the current thread assigned on its first hand over,
+    /// so threads are spread evenly between shards
+    static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Builder of aggregates that hand over completed windows to a small number of shared shards
+///
+/// Each built aggregate keeps only the window that currently receives entries,
+/// completed windows with their totals are merged into the shard of the thread
+/// the aggregate runs on. Threads are assigned to shards in turn, so a shard is shared
+/// only when there are more threads than shards. Shards are merged into a single timeline
+/// by [`Self::collect`], periodically when started via [`Self::spawn_collector`], so memory usage of the timeline
+/// does not grow with the number of virtual users.
+pub struct ShardedTimelineAggregateBuilder<S> {
+    builder: TimelineAggregateBuilder<S>,
+    shards: Shards<S>,
+    collector: Mutex<TimelineAggregate<S>>,
+}
+
+/// Aggregate that hands over completed windows to a shared shard
+///
+/// Remaining values are handed over on drop of the aggregate
+pub struct ShardedTimelineAggregate<S>
+where
+    S: AggregateStorage,
+{
+    local: Option<TimelineAggregate<S>>,
+    shards: Shards<S>,
+}
+
+impl<S> ShardedTimelineAggregateBuilder<S>
+where
+    S: AggregateStorage,
+    S::Metric: Sync,
+{
+    pub fn new(storage: S, shards: usize) -> Self {
+        Self::with_settings(storage, AggregateSettings::default(), shards)
+    }
+
+    pub fn with_settings(storage: S, settings: AggregateSettings, shards: usize) -> Self {
+        let builder = TimelineAggregateBuilder::with_settings(storage, settings);
+
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| Mutex::new(builder.build_collector()))
+                .collect(),
+            collector: Mutex::new(builder.build_collector()),
+            builder,
+        }
+    }
+
+    /// Merges windows handed over to shards into a single timeline
+    ///
+    /// Meant to be called periodically, e.g. via [`Self::spawn_collector`],
+    /// so shards hold only windows handed over since the previous collection.
+    pub fn collect(&self) {
+        for shard in self.shards.iter() {
+            let handed_over = lock(shard).take_all();
+            handed_over.merge_into(&mut lock(&self.collector));
+        }
+    }
+
+    /// Spawns a task on the current runtime that collects shards every `period`
+    ///
+    /// Task completes once the builder is dropped, values collected so far
+    /// are still available via [`Self::flush`] until then.
+    pub fn spawn_collector(self: &Arc<Self>, period: Duration) -> JoinHandle<()>
+    where
+        S: Send + Sync + 'static,
+        S::Metric: Send,
+    {
+        let builder = Arc::downgrade(self);
+
+        tokio::spawn(async move {
+            let mut ticks = interval(period);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticks.tick().await;
+
+            loop {
+                ticks.tick().await;
+                match builder.upgrade() {
+                    Some(builder) => builder.collect(),
+                    None => return,
+                }
+            }
+        })
+    }
+
+    /// Takes values collected so far, including windows not yet merged by [`Self::collect`]
+    ///
+    /// Values of the windows still owned by active aggregates are not included.
+    pub fn flush(&self) -> TimelineAggregate<S> {
+        self.collect();
+
+        std::mem::replace(&mut *lock(&self.collector), self.builder.build_collector())
+    }
+}
+
+impl<S> MetricAggregateBuilder for ShardedTimelineAggregateBuilder<S>
+where
+    S: AggregateStorage,
+    S::Metric: Sync,
+{
+    type Reporter = ShardedTimelineAggregate<S>;
+
+    fn build(&self) -> Self::Reporter {
+        ShardedTimelineAggregate {
+            local: Some(self.builder.build()),
+            shards: self.shards.clone(),
+        }
+    }
+}
+
+impl<S> ShardedTimelineAggregate<S>
+where
+    S: AggregateStorage,
+{
+    fn hand_over_closed_windows(&mut self) {
+        if let Some(closed) = self.local.as_mut().and_then(TimelineAggregate::take_closed) {
+            hand_over(&self.shards, closed);
+        }
+    }
+}
+
+impl<S> MetricAggregate for ShardedTimelineAggregate<S>
+where
+    S: AggregateStorage,
+{
+    type Metric = S::Metric;
+
+    fn add_entry(
+        &mut self,
+        metric: Self::Metric,
+        latency: Duration,
+        error: Option<&MetricRecordError>,
+    ) {
+        self.hand_over_closed_windows();
+
+        if let Some(local) = self.local.as_mut() {
+            local.add_entry(metric, latency, error);
+        }
+    }
+
+    fn add_value(&mut self, metric: Self::Metric, value: MetricValue) {
+        self.hand_over_closed_windows();
+
+        if let Some(local) = self.local.as_mut() {
+            local.add_value(metric, value);
+        }
+    }
+
+    fn add_relation(&mut self, child: &str, parent: &str) {
+        if let Some(local) = self.local.as_mut() {
+            local.add_relation(child, parent);
+        }
+    }
+
+    fn in_flight(&self) -> Option<&InFlightTracker> {
+        self.local.as_ref().and_then(MetricAggregate::in_flight)
+    }
+
+    fn merge_into(mut self, other: &mut Self) {
+        let Some(mut local) = self.local.take() else {
+            return;
+        };
+
+        match other.local.as_mut() {
+            Some(other) => local.merge_into(other),
+            None => hand_over(&other.shards, local.take_all()),
+        }
+    }
+}
+
+impl<S> Drop for ShardedTimelineAggregate<S>
+where
+    S: AggregateStorage,
+{
+    fn drop(&mut self) {
+        if let Some(mut local) = self.local.take() {
+            hand_over(&self.shards, local.take_all());
+        }
+    }
+}
+
+/// Merges aggregate into the shard of the current thread
+fn hand_over<S>(shards: &Shards<S>, aggregate: TimelineAggregate<S>)
+where
+    S: AggregateStorage,
+{
+    let shard = THREAD.with(|thread| *thread % shards.len());
+
+    aggregate.merge_into(&mut lock(&shards[shard]));
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::{advance, sleep};
+
+    use super::*;
+
+    #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+    enum ReportMetric {
+        One,
+        Two,
+    }
+
+    impl Metric for ReportMetric {
+        fn name(&self) -> &str {
+            "report_metric"
+        }
+    }
+
+    fn create_builder(
+        shards: usize,
+    ) -> ShardedTimelineAggregateBuilder<MetricAggregateStorage<ReportMetric>> {
+        ShardedTimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_scale(AggregateScale::Milliseconds),
+            shards,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn collects_values_of_all_users_from_shards() {
+        let builder = create_builder(2);
+        let mut users: Vec<_> = (0..4).map(|_| builder.build()).collect();
+
+        for (index, user) in users.iter_mut().enumerate() {
+            user.add_entry(
+                ReportMetric::One,
+                Duration::from_millis(10 + index as u64),
+                None,
+            );
+        }
+
+        advance(Duration::from_millis(100)).await;
+
+        for user in users.iter_mut() {
+            user.add_entry(ReportMetric::Two, Duration::from_millis(30), None);
+        }
+
+        drop(users);
+
+        let (total, timeline) = builder.flush().flush();
+
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].count(ReportMetric::One), 4);
+        assert_eq!(timeline[0].min_value(ReportMetric::One), 10);
+        assert_eq!(timeline[0].max_value(ReportMetric::One), 13);
+        assert_eq!(timeline[0].users(), 4);
+        assert_eq!(timeline[1].count(ReportMetric::Two), 4);
+        assert_eq!(total.total_count(), 8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hands_over_completed_windows_while_user_is_active() {
+        let builder = create_builder(1);
+        let mut user = builder.build();
+
+        user.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        advance(Duration::from_millis(100)).await;
+        user.add_entry(ReportMetric::One, Duration::from_millis(20), None);
+
+        let (total, timeline) = builder.flush().flush();
+
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(*timeline[0].time(), Duration::ZERO);
+        assert_eq!(timeline[0].max_value(ReportMetric::One), 10);
+        assert_eq!(total.total_count(), 1);
+
+        drop(user);
+
+        let (total, timeline) = builder.flush().flush();
+
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(*timeline[0].time(), Duration::from_millis(100));
+        assert_eq!(timeline[0].max_value(ReportMetric::One), 20);
+        assert_eq!(total.max_value(ReportMetric::One), 20);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_only_current_window_in_active_aggregate() {
+        let builder = create_builder(1);
+        let mut user = builder.build();
+
+        for latency in 1..=3 {
+            user.add_entry(ReportMetric::One, Duration::from_millis(latency), None);
+            advance(Duration::from_millis(100)).await;
+        }
+        user.add_value(ReportMetric::Two, MetricValue::Counter(1));
+
+        let (total, timeline) = builder.flush().flush();
+        let (local_total, local_timeline) = user.local.take().unwrap().flush();
+
+        assert_eq!((timeline.len(), total.total_count()), (3, 3));
+        assert_eq!((local_timeline.len(), local_total.total_count()), (1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn collects_shards_periodically_till_builder_is_dropped() {
+        let builder = Arc::new(create_builder(1));
+        let collector = builder.spawn_collector(Duration::from_millis(100));
+        let mut user = builder.build();
+
+        user.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        advance(Duration::from_millis(100)).await;
+        user.add_entry(ReportMetric::One, Duration::from_millis(20), None);
+        sleep(Duration::from_millis(150)).await;
+
+        let (_, shard) = lock(&builder.shards[0]).take_all().flush();
+        assert!(shard.is_empty());
+
+        drop(user);
+        let (total, timeline) = builder.flush().flush();
+
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(total.count(ReportMetric::One), 2);
+
+        drop(builder);
+        collector.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn merges_values_into_other_aggregate() {
+        let builder = create_builder(2);
+        let (mut one, mut two) = (builder.build(), builder.build());
+
+        one.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        two.add_entry(ReportMetric::One, Duration::from_millis(20), None);
+
+        one.merge_into(&mut two);
+        drop(two);
+
+        let (total, _) = builder.flush().flush();
+
+        assert_eq!(total.count(ReportMetric::One), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hands_over_values_when_other_aggregate_has_none() {
+        let builder = create_builder(2);
+        let (mut one, mut two) = (builder.build(), builder.build());
+
+        one.add_entry(ReportMetric::One, Duration::from_millis(10), None);
+        let taken = two.local.take();
+
+        one.merge_into(&mut two);
+        drop(taken);
+
+        let (total, _) = builder.flush().flush();
+
+        assert_eq!(total.count(ReportMetric::One), 1);
+    }
+}
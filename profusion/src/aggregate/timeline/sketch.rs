@@ -0,0 +1,132 @@
+use crate::aggregate::{DDSketch, SketchAggregateStorage};
+use crate::metric::{Metric, MetricValueKind};
+
+use super::{item::per_second, TimelineItem};
+
+impl<T> TimelineItem<SketchAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    pub fn min_value(&self, metric: T) -> u64 {
+        self.as_view().min_value(metric)
+    }
+
+    pub fn max_value(&self, metric: T) -> u64 {
+        self.as_view().max_value(metric)
+    }
+
+    pub fn mean_value(&self, metric: T) -> f64 {
+        self.as_view().mean_value(metric)
+    }
+
+    /// Number of recorded operations for a metric
+    pub fn count(&self, metric: T) -> u64 {
+        self.as_view().count(metric)
+    }
+
+    /// Recorded operations per second for a metric
+    pub fn throughput(&self, metric: T) -> f64 {
+        self.as_view().throughput(metric)
+    }
+
+    pub fn percentile_value<P: Into<f64>>(&self, metric: T, percentile: P) -> u64 {
+        self.as_view().percentile_value(metric, percentile)
+    }
+
+    /// Sketch of non-latency values of a kind recorded for a metric
+    pub fn metric_value(&self, metric: T, kind: MetricValueKind) -> &DDSketch {
+        self.storage().metric_value(metric, kind)
+    }
+}
+
+impl<T> TimelineItem<&SketchAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    pub fn min_value(&self, metric: T) -> u64 {
+        self.storage().value(metric).min()
+    }
+
+    pub fn max_value(&self, metric: T) -> u64 {
+        self.storage().value(metric).max()
+    }
+
+    pub fn mean_value(&self, metric: T) -> f64 {
+        self.storage().value(metric).mean()
+    }
+
+    /// Number of recorded operations for a metric
+    pub fn count(&self, metric: T) -> u64 {
+        self.storage().value(metric).len()
+    }
+
+    /// Recorded operations per second for a metric
+    pub fn throughput(&self, metric: T) -> f64 {
+        per_second(self.count(metric), self.window())
+    }
+
+    pub fn percentile_value<P: Into<f64>>(&self, metric: T, percentile: P) -> u64 {
+        self.storage().value(metric).value_at_percentile(percentile.into())
+    }
+
+    /// Sketch of non-latency values of a kind recorded for a metric
+    pub fn metric_value(&self, metric: T, kind: MetricValueKind) -> &DDSketch {
+        self.storage().metric_value(metric, kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::metric::MetricValue;
+
+    use super::*;
+
+    #[test]
+    fn reports_statistics_per_metric() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            SketchAggregateStorage::default(),
+            0,
+            1,
+        );
+
+        item.record("one", 50);
+        item.record("one", 150);
+        item.record("two", 12800);
+
+        assert_eq!(item.min_value("one"), 50);
+        assert_eq!(item.max_value("two"), 12800);
+        assert_eq!(item.mean_value("one"), 100.0);
+        assert_eq!(item.count("one"), 2);
+        assert_eq!(item.throughput("one"), 4.0);
+        assert_eq!(item.percentile_value("one", 100), 150);
+        assert_eq!(item.percentile_value("three", 99), 0);
+    }
+
+    #[test]
+    fn reports_values_without_counting_operations() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            SketchAggregateStorage::default(),
+            0,
+            1,
+        );
+
+        item.record("one", 50);
+        item.record_value("one", MetricValue::Bytes(2000));
+
+        assert_eq!(item.count("one"), 1);
+        assert_eq!(item.percentile_value("one", 100), 50);
+        assert_eq!(item.metric_value("one", MetricValueKind::Bytes).max(), 2000);
+        assert_eq!(
+            item.as_view()
+                .metric_value("one", MetricValueKind::Bytes)
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::aggregate::{SummaryAggregateStorage, SummaryValue};
+use crate::metric::{Metric, MetricValueKind};
+
+use super::{item::per_second, TimelineItem};
+
+impl<T> TimelineItem<SummaryAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    pub fn min_value(&self, metric: T) -> u64 {
+        self.as_view().min_value(metric)
+    }
+
+    pub fn max_value(&self, metric: T) -> u64 {
+        self.as_view().max_value(metric)
+    }
+
+    pub fn mean_value(&self, metric: T) -> f64 {
+        self.as_view().mean_value(metric)
+    }
+
+    /// Standard deviation of recorded values for a metric
+    pub fn std_dev(&self, metric: T) -> f64 {
+        self.as_view().std_dev(metric)
+    }
+
+    /// Number of recorded operations for a metric
+    pub fn count(&self, metric: T) -> u64 {
+        self.as_view().count(metric)
+    }
+
+    /// Recorded operations per second for a metric
+    pub fn throughput(&self, metric: T) -> f64 {
+        self.as_view().throughput(metric)
+    }
+
+    /// Summary of non-latency values of a kind recorded for a metric
+    pub fn metric_value(&self, metric: T, kind: MetricValueKind) -> SummaryValue {
+        self.as_view().metric_value(metric, kind)
+    }
+}
+
+impl<T> TimelineItem<&SummaryAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    pub fn min_value(&self, metric: T) -> u64 {
+        self.storage().value(metric).min()
+    }
+
+    pub fn max_value(&self, metric: T) -> u64 {
+        self.storage().value(metric).max()
+    }
+
+    pub fn mean_value(&self, metric: T) -> f64 {
+        self.storage().value(metric).mean()
+    }
+
+    /// Standard deviation of recorded values for a metric
+    pub fn std_dev(&self, metric: T) -> f64 {
+        self.storage().value(metric).std_dev()
+    }
+
+    /// Number of recorded operations for a metric
+    pub fn count(&self, metric: T) -> u64 {
+        self.storage().value(metric).count()
+    }
+
+    /// Recorded operations per second for a metric
+    pub fn throughput(&self, metric: T) -> f64 {
+        per_second(self.count(metric), self.window())
+    }
+
+    /// Summary of non-latency values of a kind recorded for a metric
+    pub fn metric_value(&self, metric: T, kind: MetricValueKind) -> SummaryValue {
+        self.storage().metric_value(metric, kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::metric::MetricValue;
+
+    use super::*;
+
+    #[test]
+    fn reports_summary_per_metric() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            SummaryAggregateStorage::default(),
+            0,
+            1,
+        );
+
+        item.record("one", 50);
+        item.record("one", 150);
+        item.record("two", 12800);
+
+        assert_eq!(item.min_value("one"), 50);
+        assert_eq!(item.max_value("two"), 12800);
+        assert_eq!(item.mean_value("one"), 100.0);
+        assert_eq!(item.std_dev("one"), 50.0);
+        assert_eq!(item.count("one"), 2);
+        assert_eq!(item.throughput("one"), 4.0);
+        assert_eq!(item.count("three"), 0);
+    }
+
+    #[test]
+    fn reports_values_without_counting_operations() {
+        let mut item = TimelineItem::new(
+            Duration::from_millis(10),
+            Duration::from_millis(500),
+            SummaryAggregateStorage::default(),
+            0,
+            1,
+        );
+
+        item.record("one", 50);
+        item.record_value("one", MetricValue::Bytes(2000));
+
+        assert_eq!(item.count("one"), 1);
+        assert_eq!(item.max_value("one"), 50);
+        assert_eq!(item.metric_value("one", MetricValueKind::Bytes).sum(), 2000);
+    }
+}
@@ -1,9 +1,52 @@
 use crate::metric::Metric;
 use crate::prelude::TotalAggregateStorage;
 
-use super::TimelineItem;
+use super::{
+    item::{log_histogram, per_second},
+    TimelineItem,
+};
 
 impl<T> TimelineItem<TotalAggregateStorage<T>>
+where
+    T: Metric + Send,
+{
+    pub fn min_value(&self) -> u64 {
+        self.as_view().min_value()
+    }
+
+    pub fn max_value(&self) -> u64 {
+        self.as_view().max_value()
+    }
+
+    pub fn mean_value(&self) -> f64 {
+        self.as_view().mean_value()
+    }
+
+    /// Number of recorded operations
+    pub fn count(&self) -> u64 {
+        self.as_view().count()
+    }
+
+    /// Recorded operations per second
+    pub fn throughput(&self) -> f64 {
+        self.as_view().throughput()
+    }
+
+    /// Number of values above the limit of the histogram, see [`OverflowPolicy`](crate::aggregate::OverflowPolicy)
+    pub fn overflow_count(&self) -> u64 {
+        self.as_view().overflow_count()
+    }
+
+    pub fn percentile_value<P: Into<f64>>(&self, percentile: P) -> u64 {
+        self.as_view().percentile_value(percentile)
+    }
+
+    pub fn histogram(&self) -> Vec<(u64, f64, u64)> {
+        self.as_view().histogram()
+    }
+}
+
+impl<T> TimelineItem<&TotalAggregateStorage<T>>
 where
     T: Metric + Send,
 {
@@ -19,23 +62,29 @@ where
         self.storage().value().mean()
     }
 
+    /// Number of recorded operations
+    ///
+    /// Includes operations with latencies dropped above the limit of the histogram
+    pub fn count(&self) -> u64 {
+        self.storage().count()
+    }
+
+    /// Recorded operations per second
+    pub fn throughput(&self) -> f64 {
+        per_second(self.count(), self.window())
+    }
+
+    /// Number of values above the limit of the histogram, see [`OverflowPolicy`](crate::aggregate::OverflowPolicy)
+    pub fn overflow_count(&self) -> u64 {
+        self.storage().overflows()
+    }
+
     pub fn percentile_value<P: Into<f64>>(&self, percentile: P) -> u64 {
         self.storage().value().value_at_percentile(percentile.into())
     }
 
     pub fn histogram(&self) -> Vec<(u64, f64, u64)> {
-        let histogram = self.storage().value();
-        let total_counts = histogram.len();
-        histogram
-            .iter_log(1, 2.0)
-            .map(|value| {
-                (
-                    value.value_iterated_to() + 1,
-                    (value.count_since_last_iteration() as f64 / total_counts as f64) * 100.0,
-                    value.count_since_last_iteration(),
-                )
-            })
-            .collect::<Vec<_>>()
+        log_histogram(self.storage().value())
     }
 }
 
@@ -48,6 +97,7 @@ mod tests {
     fn populate_timeline_item() -> TimelineItem<TotalAggregateStorage<&'static str>> {
         let mut item = TimelineItem::new(
             Duration::from_millis(10),
+            Duration::from_millis(500),
             TotalAggregateStorage::default(),
             0,
             1,
@@ -87,6 +137,18 @@ mod tests {
         assert_eq!(item.mean_value(), 3730.25);
     }
 
+    #[test]
+    fn counts_operations_for_all_metrics() {
+        let item = populate_timeline_item();
+        assert_eq!(item.count(), 12);
+    }
+
+    #[test]
+    fn calculates_throughput_for_all_metrics() {
+        let item = populate_timeline_item();
+        assert_eq!(item.throughput(), 24.0);
+    }
+
     #[test]
     fn calculates_percentiles_per_metric() {
         let item = populate_timeline_item();
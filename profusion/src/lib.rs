@@ -7,13 +7,19 @@ pub mod aggregate;
 pub mod measurer;
 pub mod metric;
 pub mod scenario;
+pub mod think_time;
 
+mod random;
 mod start_time;
 
 pub mod prelude {
+    #[cfg(feature = "macros")]
+    pub use profusion_macros::Metric;
+
     pub use super::aggregate::*;
     pub use super::measurer::*;
     pub use super::metric::*;
     pub use super::scenario::*;
     pub use super::start_time::*;
+    pub use super::think_time::*;
 }
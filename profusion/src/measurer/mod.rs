@@ -0,0 +1,971 @@
+/*
+ * Copyright © 2024. EcomDev B.V.
+ * All rights reserved.
+ * See LICENSE for license details.
+ */
+
+use std::{
+    convert::Infallible,
+    error::Error,
+    future::Future,
+    time::Duration,
+};
+
+use rustc_hash::FxHashMap;
+use tokio::time::{Instant, sleep, sleep_until, timeout};
+
+use crate::aggregate::{InFlightMetric, InFlightOperation, MetricAggregate};
+use crate::metric::{Metric, MetricRecordError, MetricValue};
+use crate::random::Random;
+use crate::think_time::ThinkTime;
+
+pub use stream::*;
+pub use transaction::*;
+
+mod stream;
+mod transaction;
+
+/// Default seed of think time distributions, so runs are reproducible unless specified otherwise
+const DEFAULT_SEED: u64 = 0x5EED;
+
+/// Metric measurer
+///
+/// Used in each test case virtual user to measure async operation time
+///
+pub struct MetricMeasurer<T>
+where
+    T: MetricAggregate,
+{
+    aggregate: T,
+    timeout: Option<Duration>,
+    metric_timeouts: FxHashMap<T::Metric, Duration>,
+    in_flight: FxHashMap<T::Metric, InFlightMetric>,
+    seed: u64,
+    random: Random,
+    paced_at: Instant,
+    transactions: Vec<OpenTransaction>,
+}
+
+/// Open transaction that groups measurements of child metrics
+struct OpenTransaction {
+    metric: Box<str>,
+    start: Instant,
+    timeout: Option<Duration>,
+    failed: Option<String>,
+}
+
+impl<M> MetricMeasurer<M>
+where
+    M: MetricAggregate,
+{
+    pub fn with_timeout(aggregate: M, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..Self::new(aggregate)
+        }
+    }
+
+    pub fn new(aggregate: M) -> Self {
+        Self {
+            aggregate,
+            timeout: None,
+            metric_timeouts: FxHashMap::default(),
+            in_flight: FxHashMap::default(),
+            seed: DEFAULT_SEED,
+            random: Random::for_stream(DEFAULT_SEED, 0),
+            paced_at: Instant::now(),
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Timeout for measurements of a specific metric, takes precedence over the global timeout
+    pub fn with_metric_timeout(mut self, metric: M::Metric, timeout: Duration) -> Self {
+        self.metric_timeouts.insert(metric, timeout);
+        self
+    }
+
+    /// Seed of random think time distributions
+    ///
+    /// Measurers with the same seed sample the same think times,
+    /// see [`Self::with_stream`] for reproducible runs of multiple virtual users
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            seed,
+            random: Random::new(seed),
+            ..self
+        }
+    }
+
+    /// Seed of random think time distributions with a stream derived from it,
+    /// e.g. for each virtual user, so users of a reproducible run do not sample in lockstep
+    ///
+    /// Measurers sample from the same stream unless runner or [`ScenarioMix`](crate::scenario::ScenarioMix)
+    /// assigns one per virtual user
+    pub fn with_stream(mut self, seed: u64, stream: u64) -> Self {
+        self.seed = seed;
+        self.assign_stream(stream);
+        self
+    }
+
+    /// Switches think time distributions to a stream derived from the seed of the measurer
+    pub(crate) fn assign_stream(&mut self, stream: u64) {
+        self.random = Random::for_stream(self.seed, stream);
+    }
+
+    /// Pauses virtual user for a sampled think time without recording it
+    pub async fn think(&mut self, think_time: impl Into<ThinkTime>) {
+        let duration = think_time.into().sample(&mut self.random);
+
+        if !duration.is_zero() {
+            sleep(duration).await;
+        }
+    }
+
+    /// Waits until at least `interval` has passed since the previous pacing point
+    ///
+    /// Called at the end of an iteration it makes each iteration take at least `interval`,
+    /// the first iteration is paced from the creation of the measurer.
+    /// Iterations that took longer than `interval` continue without waiting.
+    pub async fn pace(&mut self, interval: Duration) {
+        sleep_until(self.paced_at + interval).await;
+        self.paced_at = Instant::now();
+    }
+
+    pub async fn measure<T>(
+        &mut self,
+        metric: M::Metric,
+        action: impl Future<Output = T>,
+    ) -> Result<T, MetricRecordError> {
+        self.try_measure(metric, async { Ok::<_, Infallible>(action.await) })
+            .await
+    }
+
+    pub async fn try_measure<T, E>(
+        &mut self,
+        metric: M::Metric,
+        action: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, MetricRecordError>
+    where
+        E: Error + 'static,
+    {
+        let timeout = self.metric_timeout(&metric);
+        self.measure_within(metric, timeout, action).await
+    }
+
+    /// Measures future with a timeout that overrides configured ones for this call only
+    pub async fn measure_with_timeout<T>(
+        &mut self,
+        metric: M::Metric,
+        timeout: Duration,
+        action: impl Future<Output = T>,
+    ) -> Result<T, MetricRecordError> {
+        self.try_measure_with_timeout(metric, timeout, async {
+            Ok::<_, Infallible>(action.await)
+        })
+        .await
+    }
+
+    /// Measures fallible future with a timeout that overrides configured ones for this call only
+    pub async fn try_measure_with_timeout<T, E>(
+        &mut self,
+        metric: M::Metric,
+        timeout: Duration,
+        action: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, MetricRecordError>
+    where
+        E: Error + 'static,
+    {
+        self.measure_within(metric, Some(timeout), action).await
+    }
+
+    async fn measure_within<T, E>(
+        &mut self,
+        metric: M::Metric,
+        timeout: Option<Duration>,
+        action: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, MetricRecordError>
+    where
+        E: Error + 'static,
+    {
+        let in_flight = self.start_in_flight(metric);
+        let start = Instant::now();
+
+        let result = self.execute_with_timeout(timeout, action).await;
+
+        drop(in_flight);
+
+        self.add_measurement(metric, start.elapsed(), result.as_ref().err());
+
+        result
+    }
+
+    pub fn add_measurement(
+        &mut self,
+        metric: M::Metric,
+        latency: Duration,
+        error: Option<&MetricRecordError>,
+    ) {
+        if let Some(parent) = self.transactions.last_mut() {
+            self.aggregate.add_relation(metric.name(), &parent.metric);
+
+            if error.is_some() && parent.failed.is_none() {
+                parent.failed = Some(metric.name().to_string());
+            }
+        }
+
+        self.aggregate.add_entry(metric, latency, error);
+    }
+
+    /// Records non-latency value of a metric, e.g. size of a response
+    pub fn add_value(&mut self, metric: M::Metric, value: MetricValue) {
+        if let Some(parent) = self.transactions.last() {
+            self.aggregate.add_relation(metric.name(), &parent.metric);
+        }
+
+        self.aggregate.add_value(metric, value);
+    }
+
+    /// Starts transaction that spans all measurements made through it until it is completed
+    ///
+    /// Measurements within transaction are reported as children of its metric,
+    /// transactions can be nested. When measurer has a timeout configured for the transaction
+    /// metric via [`Self::with_metric_timeout`], it limits the whole transaction, so each child
+    /// measurement gets only the remaining time. Global timeout applies only to children.
+    pub fn transaction(&mut self, metric: M::Metric) -> Transaction<'_, M> {
+        self.transactions.push(OpenTransaction {
+            metric: metric.name().into(),
+            start: Instant::now(),
+            timeout: self.configured_timeout(&metric),
+            failed: None,
+        });
+
+        Transaction::new(self, metric)
+    }
+
+    /// Starts in-flight operation of a metric when aggregate tracks them
+    ///
+    /// Metric is resolved in the shared tracker only on its first operation
+    fn start_in_flight(&mut self, metric: M::Metric) -> Option<InFlightOperation> {
+        let tracker = self.aggregate.in_flight()?;
+        let in_flight = self
+            .in_flight
+            .entry(metric)
+            .or_insert_with(|| tracker.metric(&metric));
+
+        Some(in_flight.start())
+    }
+
+    fn metric_timeout(&self, metric: &M::Metric) -> Option<Duration> {
+        self.configured_timeout(metric).or(self.timeout)
+    }
+
+    /// Timeout configured for the metric via [`Self::with_metric_timeout`]
+    fn configured_timeout(&self, metric: &M::Metric) -> Option<Duration> {
+        match self.metric_timeouts.is_empty() {
+            true => None,
+            false => self.metric_timeouts.get(metric).copied(),
+        }
+    }
+
+    /// Open transaction with the earliest deadline and its deadline
+    fn earliest_deadline(&self) -> Option<(&OpenTransaction, Instant)> {
+        self.transactions
+            .iter()
+            .filter_map(|transaction| {
+                transaction.timeout.map(|timeout| (transaction, transaction.start + timeout))
+            })
+            .min_by_key(|(_, deadline)| *deadline)
+    }
+
+    /// Time limit of the next measurement, capped by the earliest deadline of open transactions
+    fn effective_timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let remaining = self
+            .earliest_deadline()
+            .map(|(_, deadline)| deadline.saturating_duration_since(Instant::now()));
+
+        match (timeout, remaining) {
+            (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+            (timeout, remaining) => timeout.or(remaining),
+        }
+    }
+
+    /// Error of a measurement that reached its time limit
+    ///
+    /// Reports configured timeout of the measurement rather than the time left
+    /// in a transaction, falls back to timeout of the transaction when none is configured
+    fn timeout_error(&self, timeout: Option<Duration>, limit: Duration) -> MetricRecordError {
+        MetricRecordError::Timeout(
+            timeout
+                .or_else(|| {
+                    self.earliest_deadline().and_then(|(transaction, _)| transaction.timeout)
+                })
+                .unwrap_or(limit),
+        )
+    }
+
+    async fn execute_with_timeout<T, E>(
+        &self,
+        timeout_override: Option<Duration>,
+        action: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, MetricRecordError>
+    where
+        E: Error + 'static,
+    {
+        match self.effective_timeout(timeout_override) {
+            Some(max_duration) => match timeout(max_duration, action).await {
+                Ok(result) => result.map_err(|e| MetricRecordError::Dynamic(Box::new(e))),
+                Err(_) => Err(self.timeout_error(timeout_override, max_duration)),
+            },
+            None => action
+                .await
+                .map_err(|e| MetricRecordError::Dynamic(Box::new(e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Debug, hash::Hash, io::ErrorKind, time::Duration};
+
+    use tokio::{
+        task::yield_now,
+        time::{advance, sleep, Instant},
+    };
+
+    use crate::aggregate::{
+        AggregateSettings, MetricAggregate, MetricAggregateBuilder, MetricAggregateStorage,
+        TestAggregate, TestAggregateBuilder, TimelineAggregateBuilder,
+    };
+    use crate::measurer::MetricMeasurer;
+    use crate::metric::{Metric, MetricRecordError};
+    use crate::think_time::ThinkTime;
+
+    #[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Hash, Copy, Clone)]
+    #[allow(clippy::enum_variant_names)]
+    enum TestMetric {
+        MetricOne,
+        MetricTwo,
+        MetricThree,
+    }
+
+    impl Metric for TestMetric {
+        fn name(&self) -> &'static str {
+            match self {
+                Self::MetricOne => "metric_one",
+                Self::MetricTwo => "metric_two",
+                Self::MetricThree => "metric_three",
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_measurements_from_future_without_return_value() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        recorder
+            .measure(TestMetric::MetricOne, async {
+                advance(Duration::from_millis(30)).await;
+            })
+            .await
+            .unwrap();
+
+        recorder
+            .measure(TestMetric::MetricTwo, async {
+                advance(Duration::from_millis(20)).await;
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (TestMetric::MetricOne, Duration::from_millis(30), false),
+                (TestMetric::MetricTwo, Duration::from_millis(20), false),
+            ],
+            recorder.aggregate.values()
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_timeout_error_when_timeout_is_specified() {
+        let mut recorder = MetricMeasurer::with_timeout(
+            TestAggregateBuilder::new().build(),
+            Duration::from_millis(10),
+        );
+
+        let result = recorder
+            .measure(TestMetric::MetricOne, async {
+                advance(Duration::from_millis(5)).await;
+                advance(Duration::from_millis(5)).await;
+                yield_now().await;
+                advance(Duration::from_millis(5)).await;
+            })
+            .await;
+
+        assert!(result.is_err());
+
+        assert_eq!(
+            vec![(TestMetric::MetricOne, Duration::from_millis(10), true)],
+            recorder.aggregate.values(),
+            "recorded metric contains error flag and equal to timeout value"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_record_think_time() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+        let start = Instant::now();
+
+        recorder.think(Duration::from_millis(300)).await;
+        recorder
+            .measure(TestMetric::MetricOne, async {
+                advance(Duration::from_millis(10)).await;
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(start.elapsed(), Duration::from_millis(310));
+        assert_eq!(
+            vec![(TestMetric::MetricOne, Duration::from_millis(10), false)],
+            recorder.aggregate.values()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn samples_same_think_time_for_same_seed() {
+        let think_time = ThinkTime::Uniform {
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        let mut elapsed = Vec::new();
+
+        for _ in 0..2 {
+            let mut recorder =
+                MetricMeasurer::new(TestAggregateBuilder::<TestMetric>::new().build())
+                    .with_seed(42);
+            let start = Instant::now();
+
+            recorder.think(think_time).await;
+            recorder.think(think_time).await;
+
+            elapsed.push(start.elapsed());
+        }
+
+        assert_eq!(elapsed[0], elapsed[1]);
+        assert!(elapsed[0] >= Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn samples_think_time_from_assigned_stream() {
+        let think_time = ThinkTime::Uniform {
+            min: Duration::ZERO,
+            max: Duration::from_secs(1000),
+        };
+        let mut elapsed = Vec::new();
+
+        for recorder in [
+            MetricMeasurer::new(TestAggregateBuilder::<TestMetric>::new().build()),
+            MetricMeasurer::new(TestAggregateBuilder::<TestMetric>::new().build()),
+            MetricMeasurer::new(TestAggregateBuilder::<TestMetric>::new().build())
+                .with_stream(42, 0),
+            MetricMeasurer::new(TestAggregateBuilder::<TestMetric>::new().build())
+                .with_stream(42, 1),
+        ]
+        .iter_mut()
+        {
+            let start = Instant::now();
+            recorder.think(think_time).await;
+            elapsed.push(start.elapsed());
+        }
+
+        assert_eq!(elapsed[0], elapsed[1]);
+        assert_ne!(elapsed[2], elapsed[3]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paces_iterations_to_take_at_least_interval() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+        let start = Instant::now();
+
+        for iteration_time in [500, 2500, 1000] {
+            recorder
+                .measure(TestMetric::MetricOne, async {
+                    advance(Duration::from_millis(iteration_time)).await;
+                })
+                .await
+                .unwrap();
+
+            recorder.pace(Duration::from_secs(2)).await;
+        }
+
+        assert_eq!(start.elapsed(), Duration::from_millis(6500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_transaction_spanning_child_measurements() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let mut transaction = recorder.transaction(TestMetric::MetricOne);
+        for _ in 0..2 {
+            transaction
+                .measure(TestMetric::MetricTwo, async {
+                    advance(Duration::from_millis(20)).await;
+                })
+                .await
+                .unwrap();
+        }
+        transaction.end().unwrap();
+
+        assert_eq!(
+            recorder.aggregate.relations(),
+            vec![("metric_two", "metric_one"), ("metric_two", "metric_one")]
+        );
+        assert_eq!(
+            vec![
+                (TestMetric::MetricTwo, Duration::from_millis(20), false),
+                (TestMetric::MetricTwo, Duration::from_millis(20), false),
+                (TestMetric::MetricOne, Duration::from_millis(40), false),
+            ],
+            recorder.aggregate.values()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_transaction_when_child_fails() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let mut transaction = recorder.transaction(TestMetric::MetricOne);
+        let _ = transaction
+            .try_measure(TestMetric::MetricTwo, async {
+                Err::<(), _>(std::io::Error::from(ErrorKind::NotFound))
+            })
+            .await;
+
+        let result = transaction.end();
+
+        assert!(
+            matches!(result, Err(MetricRecordError::Transaction(ref child)) if child == "metric_two")
+        );
+        assert_eq!(
+            vec![
+                (TestMetric::MetricTwo, Duration::ZERO, true),
+                (TestMetric::MetricOne, Duration::ZERO, true),
+            ],
+            recorder.aggregate.values()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nests_transactions() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let mut outer = recorder.transaction(TestMetric::MetricOne);
+        let mut inner = outer.transaction(TestMetric::MetricTwo);
+        inner.add_measurement(
+            TestMetric::MetricThree,
+            Duration::from_millis(10),
+            Some(&MetricRecordError::Timeout(Duration::from_millis(10))),
+        );
+
+        assert!(inner.end().is_err());
+        assert!(outer.end().is_err());
+        assert_eq!(
+            recorder.aggregate.relations(),
+            vec![("metric_three", "metric_two"), ("metric_two", "metric_one")]
+        );
+    }
+
+    async fn checkout(
+        recorder: &mut MetricMeasurer<TestAggregate<TestMetric>>,
+    ) -> Result<(), MetricRecordError> {
+        let mut transaction = recorder.transaction(TestMetric::MetricOne);
+        transaction
+            .try_measure(TestMetric::MetricTwo, async {
+                Err::<(), _>(std::io::Error::from(ErrorKind::NotFound))
+            })
+            .await?;
+        transaction.end()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn completes_transaction_on_early_return() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build())
+            .with_metric_timeout(TestMetric::MetricOne, Duration::from_millis(100));
+
+        assert!(checkout(&mut recorder).await.is_err());
+        advance(Duration::from_millis(200)).await;
+        recorder
+            .measure(TestMetric::MetricThree, sleep(Duration::from_millis(50)))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            recorder.aggregate.relations(),
+            vec![("metric_two", "metric_one")]
+        );
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::MetricTwo, Duration::ZERO, true),
+                (TestMetric::MetricOne, Duration::ZERO, true),
+                (TestMetric::MetricThree, Duration::from_millis(50), false),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn completes_nested_transactions_when_outer_one_ends() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let mut outer = recorder.transaction(TestMetric::MetricOne);
+        std::mem::forget(outer.transaction(TestMetric::MetricTwo));
+        outer.add_measurement(TestMetric::MetricThree, Duration::from_millis(10), None);
+        outer.end().unwrap();
+        recorder.add_measurement(TestMetric::MetricThree, Duration::from_millis(10), None);
+
+        assert_eq!(
+            recorder.aggregate.relations(),
+            vec![("metric_three", "metric_two")]
+        );
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::MetricThree, Duration::from_millis(10), false),
+                (TestMetric::MetricOne, Duration::ZERO, false),
+                (TestMetric::MetricThree, Duration::from_millis(10), false),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_child_timeout_by_remaining_transaction_time() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build())
+            .with_metric_timeout(TestMetric::MetricOne, Duration::from_millis(50));
+
+        let mut transaction = recorder.transaction(TestMetric::MetricOne);
+        transaction
+            .measure(TestMetric::MetricTwo, async {
+                advance(Duration::from_millis(30)).await;
+            })
+            .await
+            .unwrap();
+
+        let result = transaction
+            .measure(TestMetric::MetricTwo, sleep(Duration::from_millis(30)))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_millis(50)
+        ));
+        assert!(transaction.end().is_err());
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::MetricTwo, Duration::from_millis(30), false),
+                (TestMetric::MetricTwo, Duration::from_millis(20), true),
+                (TestMetric::MetricOne, Duration::from_millis(50), true),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_limit_transaction_by_global_timeout() {
+        let mut recorder = MetricMeasurer::with_timeout(
+            TestAggregateBuilder::new().build(),
+            Duration::from_millis(50),
+        );
+
+        let mut transaction = recorder.transaction(TestMetric::MetricOne);
+        for _ in 0..2 {
+            transaction
+                .measure(TestMetric::MetricTwo, sleep(Duration::from_millis(40)))
+                .await
+                .unwrap();
+        }
+        transaction.end().unwrap();
+
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::MetricTwo, Duration::from_millis(40), false),
+                (TestMetric::MetricTwo, Duration::from_millis(40), false),
+                (TestMetric::MetricOne, Duration::from_millis(80), false),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn applies_timeout_configured_for_metric() {
+        let mut recorder = MetricMeasurer::with_timeout(
+            TestAggregateBuilder::new().build(),
+            Duration::from_secs(5),
+        )
+        .with_metric_timeout(TestMetric::MetricTwo, Duration::from_millis(200));
+
+        let first = recorder
+            .measure(TestMetric::MetricOne, sleep(Duration::from_secs(1)))
+            .await;
+        let second = recorder
+            .measure(TestMetric::MetricTwo, sleep(Duration::from_secs(1)))
+            .await;
+
+        assert!(first.is_ok());
+        assert!(matches!(
+            second,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_millis(200)
+        ));
+        assert_eq!(
+            vec![
+                (TestMetric::MetricOne, Duration::from_secs(1), false),
+                (TestMetric::MetricTwo, Duration::from_millis(200), true),
+            ],
+            recorder.aggregate.values()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_timeouts_of_metrics_with_same_name_apart() {
+        #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+        struct Replica(u8);
+
+        impl Metric for Replica {
+            fn name(&self) -> &str {
+                "replica"
+            }
+        }
+
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build())
+            .with_metric_timeout(Replica(1), Duration::from_millis(100));
+
+        let limited = recorder
+            .measure(Replica(1), sleep(Duration::from_millis(200)))
+            .await;
+        let unlimited = recorder
+            .measure(Replica(2), sleep(Duration::from_millis(200)))
+            .await;
+
+        assert!(limited.is_err());
+        assert!(unlimited.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn overrides_timeout_for_single_call() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build())
+            .with_metric_timeout(TestMetric::MetricOne, Duration::from_millis(200));
+
+        let overridden = recorder
+            .measure_with_timeout(
+                TestMetric::MetricOne,
+                Duration::from_secs(2),
+                sleep(Duration::from_secs(1)),
+            )
+            .await;
+        let failed = recorder
+            .try_measure_with_timeout(TestMetric::MetricTwo, Duration::from_millis(50), async {
+                sleep(Duration::from_secs(1)).await;
+                Ok::<_, std::io::Error>(())
+            })
+            .await;
+
+        assert!(overridden.is_ok());
+        assert!(matches!(
+            failed,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_millis(50)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_transaction_by_its_metric_timeout() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build())
+            .with_metric_timeout(TestMetric::MetricOne, Duration::from_millis(100));
+
+        let mut transaction = recorder.transaction(TestMetric::MetricOne);
+
+        let limited = transaction
+            .measure_with_timeout(
+                TestMetric::MetricTwo,
+                Duration::from_secs(1),
+                sleep(Duration::from_millis(500)),
+            )
+            .await;
+        let without_timeout = transaction
+            .measure(TestMetric::MetricThree, sleep(Duration::from_millis(500)))
+            .await;
+
+        assert!(matches!(
+            limited,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_secs(1)
+        ));
+        assert!(matches!(
+            without_timeout,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_millis(100)
+        ));
+        assert!(transaction.end().is_err());
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::MetricTwo, Duration::from_millis(100), true),
+                (TestMetric::MetricThree, Duration::ZERO, true),
+                (TestMetric::MetricOne, Duration::from_millis(100), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_result_of_a_run() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let result = recorder.measure(TestMetric::MetricOne, async { 1 }).await;
+
+        assert_eq!(1, result.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_successful_measurement_on_each_try_record() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        recorder
+            .try_measure(TestMetric::MetricOne, async {
+                advance(Duration::from_millis(5)).await;
+                Ok::<_, std::io::Error>(())
+            })
+            .await
+            .unwrap();
+
+        recorder
+            .try_measure(TestMetric::MetricTwo, async {
+                advance(Duration::from_millis(5)).await;
+                Ok::<_, std::io::Error>(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (TestMetric::MetricOne, Duration::from_millis(5), false),
+                (TestMetric::MetricTwo, Duration::from_millis(5), false),
+            ],
+            recorder.aggregate.values()
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_errors_when_try_records() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        recorder
+            .try_measure(TestMetric::MetricOne, async {
+                advance(Duration::from_millis(5)).await;
+                Err::<(), _>(std::io::Error::from(ErrorKind::TimedOut))
+            })
+            .await
+            .unwrap_err();
+
+        recorder
+            .try_measure(TestMetric::MetricTwo, async {
+                advance(Duration::from_millis(5)).await;
+                Err::<(), _>(std::io::Error::from(ErrorKind::TimedOut))
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            vec![
+                (TestMetric::MetricOne, Duration::from_millis(5), true),
+                (TestMetric::MetricTwo, Duration::from_millis(5), true),
+            ],
+            recorder.aggregate.values()
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_latencies_passed_manually() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        recorder.add_measurement(
+            TestMetric::MetricOne,
+            Duration::from_millis(6),
+            Some(&MetricRecordError::Timeout(Duration::from_millis(2))),
+        );
+
+        recorder.add_measurement(TestMetric::MetricTwo, Duration::from_millis(1), None);
+
+        assert_eq!(
+            vec![
+                (TestMetric::MetricOne, Duration::from_millis(6), true),
+                (TestMetric::MetricTwo, Duration::from_millis(1), false),
+            ],
+            recorder.aggregate.values()
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tracks_in_flight_operations_shared_between_measurers() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_in_flight(true),
+        );
+        let (mut first, mut second) = (
+            MetricMeasurer::new(builder.build()),
+            MetricMeasurer::new(builder.build()),
+        );
+
+        let (first_result, second_result) = tokio::join!(
+            first.measure(TestMetric::MetricOne, sleep(Duration::from_millis(40))),
+            second.measure(TestMetric::MetricOne, sleep(Duration::from_millis(20)))
+        );
+        first_result.unwrap();
+        second_result.unwrap();
+        advance(Duration::from_millis(60)).await;
+
+        let tracker = first.aggregate.in_flight().unwrap().clone();
+        second.aggregate.merge_into(&mut first.aggregate);
+        let (total, timeline) = first.aggregate.flush();
+
+        assert_eq!(tracker.in_flight(TestMetric::MetricOne), 0);
+        assert_eq!(timeline[0].peak_concurrency(TestMetric::MetricOne), 2);
+        assert!((timeline[0].mean_concurrency(TestMetric::MetricOne) - 1.2).abs() < 1e-9);
+        assert_eq!(total.peak_concurrency(TestMetric::MetricOne), 2);
+        assert!((total.mean_concurrency(TestMetric::MetricOne) - 0.6).abs() < 1e-9);
+        assert_eq!(total.peak_concurrency(TestMetric::MetricTwo), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn completes_in_flight_operation_on_timeout() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default().with_in_flight(true),
+        );
+        let mut recorder = MetricMeasurer::new(builder.build());
+
+        recorder
+            .measure_with_timeout(
+                TestMetric::MetricOne,
+                Duration::from_millis(10),
+                sleep(Duration::from_millis(50)),
+            )
+            .await
+            .unwrap_err();
+
+        let tracker = recorder.aggregate.in_flight().unwrap();
+        assert_eq!(tracker.in_flight(TestMetric::MetricOne), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resolves_in_flight_metric_once_per_measurer() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default().with_in_flight(true),
+        );
+        let mut recorder = MetricMeasurer::new(builder.build());
+
+        for _ in 0..3 {
+            recorder.measure(TestMetric::MetricOne, async {}).await.unwrap();
+        }
+        recorder.measure(TestMetric::MetricTwo, async {}).await.unwrap();
+
+        assert_eq!(recorder.in_flight.len(), 2);
+    }
+}
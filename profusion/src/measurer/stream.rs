@@ -0,0 +1,479 @@
+use std::{
+    convert::Infallible,
+    error::Error,
+    future::poll_fn,
+    pin::{pin, Pin},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use tokio::time::{timeout_at, Instant};
+
+use crate::aggregate::MetricAggregate;
+use crate::metric::{Metric, MetricRecordError, MetricValue};
+
+use super::MetricMeasurer;
+
+/// Metrics recorded for a measured stream
+///
+/// Time to the first and to the last item are recorded as latencies,
+/// number of items and bytes as [`MetricValue`] of the stream.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamMetrics<T> {
+    first_item: T,
+    last_item: T,
+    items: T,
+    bytes: T,
+    first_item_timeout: Option<Duration>,
+    total_timeout: Option<Duration>,
+}
+
+/// Outcome of a successfully measured stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamSummary {
+    time_to_first_item: Option<Duration>,
+    time_to_last_item: Option<Duration>,
+    items: u64,
+    bytes: u64,
+}
+
+impl<T> StreamMetrics<T>
+where
+    T: Metric,
+{
+    pub fn new(first_item: T, last_item: T, items: T, bytes: T) -> Self {
+        Self {
+            first_item,
+            last_item,
+            items,
+            bytes,
+            first_item_timeout: None,
+            total_timeout: None,
+        }
+    }
+
+    /// Maximum time to wait for the first item
+    ///
+    /// Defaults to timeout configured in measurer for the first item metric
+    pub fn with_first_item_timeout(self, timeout: Duration) -> Self {
+        Self {
+            first_item_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Maximum time to wait for the end of the stream
+    ///
+    /// Defaults to timeout configured in measurer for the last item metric
+    pub fn with_total_timeout(self, timeout: Duration) -> Self {
+        Self {
+            total_timeout: Some(timeout),
+            ..self
+        }
+    }
+}
+
+impl StreamSummary {
+    /// Time from the start of measurement till the first item, empty for streams without items
+    pub fn time_to_first_item(&self) -> Option<Duration> {
+        self.time_to_first_item
+    }
+
+    /// Time from the start of measurement till the last item, empty for streams without items
+    pub fn time_to_last_item(&self) -> Option<Duration> {
+        self.time_to_last_item
+    }
+
+    pub fn items(&self) -> u64 {
+        self.items
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+pin_project! {
+    struct InfallibleStream<S> {
+        #[pin]
+        inner: S,
+    }
+}
+
+impl<S> Stream for InfallibleStream<S>
+where
+    S: Stream,
+{
+    type Item = Result<S::Item, Infallible>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_next(cx).map(|item| item.map(Ok))
+    }
+}
+
+impl<M> MetricMeasurer<M>
+where
+    M: MetricAggregate,
+{
+    /// Measures stream till its end
+    ///
+    /// Each item is passed to `consume` closure, which returns number of bytes in the item
+    pub async fn measure_stream<T>(
+        &mut self,
+        metrics: StreamMetrics<M::Metric>,
+        stream: impl Stream<Item = T>,
+        consume: impl FnMut(T) -> u64,
+    ) -> Result<StreamSummary, MetricRecordError> {
+        self.try_measure_stream(metrics, InfallibleStream { inner: stream }, consume)
+            .await
+    }
+
+    /// Measures stream of results till its end or the first error
+    ///
+    /// Each item is passed to `consume` closure, which returns number of bytes in the item.
+    /// On failure latency metric of the stage where it happened is recorded with an error,
+    /// while items and bytes received so far are still recorded.
+    /// Stream that ends without items records the last item metric with time till its end.
+    /// Stream is in-flight under the last item metric until its end.
+    pub async fn try_measure_stream<T, E>(
+        &mut self,
+        metrics: StreamMetrics<M::Metric>,
+        stream: impl Stream<Item = Result<T, E>>,
+        mut consume: impl FnMut(T) -> u64,
+    ) -> Result<StreamSummary, MetricRecordError>
+    where
+        E: Error + 'static,
+    {
+        let start = Instant::now();
+        let total_timeout =
+            metrics.total_timeout.or_else(|| self.metric_timeout(&metrics.last_item));
+        let first_item_timeout = match (
+            metrics.first_item_timeout.or_else(|| self.metric_timeout(&metrics.first_item)),
+            total_timeout,
+        ) {
+            (Some(first), Some(total)) => Some(first.min(total)),
+            (first, total) => first.or(total),
+        };
+        let total_limit = self.effective_timeout(total_timeout);
+        let first_item_limit = self.effective_timeout(first_item_timeout);
+
+        let in_flight = self.start_in_flight(metrics.last_item);
+        let mut stream = pin!(stream);
+        let mut summary = StreamSummary::default();
+
+        let result = loop {
+            let (timeout, limit) = match summary.items {
+                0 => (first_item_timeout, first_item_limit),
+                _ => (total_timeout, total_limit),
+            };
+
+            let next = poll_fn(|cx| stream.as_mut().poll_next(cx));
+            let item = match limit {
+                Some(limit) => match timeout_at(start + limit, next).await {
+                    Ok(item) => item,
+                    Err(_) => break Err(self.timeout_error(timeout, limit)),
+                },
+                None => next.await,
+            };
+
+            match item {
+                Some(Ok(item)) => {
+                    let elapsed = start.elapsed();
+
+                    if summary.items == 0 {
+                        summary.time_to_first_item = Some(elapsed);
+                        self.add_measurement(metrics.first_item, elapsed, None);
+                    }
+
+                    summary.time_to_last_item = Some(elapsed);
+                    summary.items += 1;
+                    summary.bytes += consume(item);
+                }
+                Some(Err(error)) => break Err(MetricRecordError::Dynamic(Box::new(error))),
+                None => break Ok(()),
+            }
+        };
+
+        drop(in_flight);
+
+        match &result {
+            Err(error) => {
+                if summary.time_to_first_item.is_none() {
+                    self.add_measurement(metrics.first_item, start.elapsed(), Some(error));
+                }
+
+                self.add_measurement(metrics.last_item, start.elapsed(), Some(error));
+            }
+            Ok(_) => {
+                let latency = summary.time_to_last_item.unwrap_or_else(|| start.elapsed());
+                self.add_measurement(metrics.last_item, latency, None);
+            }
+        }
+
+        self.add_value(metrics.items, MetricValue::Distribution(summary.items));
+        self.add_value(metrics.bytes, MetricValue::Bytes(summary.bytes));
+
+        result.map(|_| summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, future::Future, io::ErrorKind};
+
+    use tokio::time::{advance, sleep, Sleep};
+
+    use crate::aggregate::{
+        AggregateSettings, MetricAggregate, MetricAggregateBuilder, MetricAggregateStorage,
+        TestAggregateBuilder, TimelineAggregateBuilder,
+    };
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+    enum TestMetric {
+        FirstItem,
+        LastItem,
+        Items,
+        Bytes,
+    }
+
+    impl Metric for TestMetric {
+        fn name(&self) -> &'static str {
+            match self {
+                Self::FirstItem => "first_item",
+                Self::LastItem => "last_item",
+                Self::Items => "items",
+                Self::Bytes => "bytes",
+            }
+        }
+    }
+
+    /// Stream that returns each item after a delay
+    struct DelayedStream {
+        items: VecDeque<(Duration, Result<&'static str, std::io::Error>)>,
+        delay: Option<Pin<Box<Sleep>>>,
+    }
+
+    impl DelayedStream {
+        fn new(items: Vec<(u64, Result<&'static str, std::io::Error>)>) -> Self {
+            Self {
+                items: items
+                    .into_iter()
+                    .map(|(delay, item)| (Duration::from_millis(delay), item))
+                    .collect(),
+                delay: None,
+            }
+        }
+    }
+
+    impl Stream for DelayedStream {
+        type Item = Result<&'static str, std::io::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let Some((delay, _)) = self.items.front() else {
+                return Poll::Ready(None);
+            };
+
+            let delay = *delay;
+            let sleep = self.delay.get_or_insert_with(|| Box::pin(sleep(delay)));
+
+            match sleep.as_mut().poll(cx) {
+                Poll::Ready(_) => {
+                    self.delay = None;
+                    Poll::Ready(self.items.pop_front().map(|(_, item)| item))
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    fn metrics() -> StreamMetrics<TestMetric> {
+        StreamMetrics::new(
+            TestMetric::FirstItem,
+            TestMetric::LastItem,
+            TestMetric::Items,
+            TestMetric::Bytes,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_first_and_last_item_with_transferred_bytes() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let summary = recorder
+            .try_measure_stream(
+                metrics(),
+                DelayedStream::new(vec![(100, Ok("one")), (50, Ok("three")), (50, Ok("two"))]),
+                |item| item.len() as u64,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(summary.time_to_first_item(), Some(Duration::from_millis(100)));
+        assert_eq!(summary.time_to_last_item(), Some(Duration::from_millis(200)));
+        assert_eq!(summary.items(), 3);
+        assert_eq!(summary.bytes(), 11);
+        assert_eq!(
+            recorder.aggregate.metric_values(),
+            &[
+                (TestMetric::Items, MetricValue::Distribution(3)),
+                (TestMetric::Bytes, MetricValue::Bytes(11)),
+            ]
+        );
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::FirstItem, Duration::from_millis(100), false),
+                (TestMetric::LastItem, Duration::from_millis(200), false),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn measures_infallible_stream() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+        let items = DelayedStream::new(vec![(10, Ok("one")), (10, Ok("two"))]);
+
+        let summary = recorder
+            .measure_stream(metrics(), items, |item| item.unwrap().len() as u64)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.items(), 2);
+        assert_eq!(summary.bytes(), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_empty_stream_as_completed_operation() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let summary = recorder
+            .try_measure_stream(metrics(), DelayedStream::new(vec![]), |item| {
+                item.len() as u64
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.time_to_last_item(), None);
+        assert_eq!(summary.items(), 0);
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![(TestMetric::LastItem, Duration::ZERO, false)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_when_first_item_is_not_received_in_time() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let result = recorder
+            .try_measure_stream(
+                metrics().with_first_item_timeout(Duration::from_millis(50)),
+                DelayedStream::new(vec![(100, Ok("one"))]),
+                |item| item.len() as u64,
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_millis(50)
+        ));
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::FirstItem, Duration::from_millis(50), true),
+                (TestMetric::LastItem, Duration::from_millis(50), true),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_when_stream_does_not_complete_in_time() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build())
+            .with_metric_timeout(TestMetric::LastItem, Duration::from_millis(150));
+
+        let result = recorder
+            .try_measure_stream(
+                metrics(),
+                DelayedStream::new(vec![(100, Ok("one")), (100, Ok("two"))]),
+                |item| item.len() as u64,
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(MetricRecordError::Timeout(duration)) if duration == Duration::from_millis(150)
+        ));
+        assert_eq!(
+            recorder.aggregate.metric_values(),
+            &[
+                (TestMetric::Items, MetricValue::Distribution(1)),
+                (TestMetric::Bytes, MetricValue::Bytes(3)),
+            ]
+        );
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::FirstItem, Duration::from_millis(100), false),
+                (TestMetric::LastItem, Duration::from_millis(150), true),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_on_stream_error() {
+        let mut recorder = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        let result = recorder
+            .try_measure_stream(
+                metrics(),
+                DelayedStream::new(vec![
+                    (10, Ok("one")),
+                    (10, Err(ErrorKind::ConnectionReset.into())),
+                ]),
+                |item| item.len() as u64,
+            )
+            .await;
+
+        assert!(matches!(result, Err(MetricRecordError::Dynamic(_))));
+        assert_eq!(
+            recorder.aggregate.values(),
+            vec![
+                (TestMetric::FirstItem, Duration::from_millis(10), false),
+                (TestMetric::LastItem, Duration::from_millis(20), true),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tracks_stream_as_in_flight_till_its_end() {
+        let builder = TimelineAggregateBuilder::with_settings(
+            MetricAggregateStorage::default(),
+            AggregateSettings::default()
+                .with_window(Duration::from_millis(100))
+                .with_in_flight(true),
+        );
+        let mut recorder = MetricMeasurer::new(builder.build());
+
+        recorder
+            .measure_stream(
+                metrics(),
+                DelayedStream::new(vec![(100, Ok("one")), (50, Ok("two"))]),
+                |_| 0,
+            )
+            .await
+            .unwrap();
+        advance(Duration::from_millis(50)).await;
+
+        let tracker = recorder.aggregate.in_flight().unwrap().clone();
+        let (total, _) = recorder.aggregate.flush();
+
+        assert_eq!(tracker.in_flight(TestMetric::LastItem), 0);
+        assert_eq!(total.peak_concurrency(TestMetric::LastItem), 1);
+        assert!((total.mean_concurrency(TestMetric::LastItem) - 0.75).abs() < 1e-9);
+        assert_eq!(total.peak_concurrency(TestMetric::FirstItem), 0);
+    }
+}
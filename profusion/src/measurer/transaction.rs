@@ -0,0 +1,92 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::aggregate::MetricAggregate;
+use crate::metric::MetricRecordError;
+
+use super::MetricMeasurer;
+
+/// Open transaction started via [`MetricMeasurer::transaction`]
+///
+/// Dereferences to the measurer, so child measurements and nested transactions are made
+/// through it. Transaction is completed via [`Self::end`] or when it is dropped, so an early
+/// return with `?` or a panic still records it and closes it for following measurements.
+pub struct Transaction<'a, M>
+where
+    M: MetricAggregate,
+{
+    measurer: &'a mut MetricMeasurer<M>,
+    metric: Option<M::Metric>,
+    depth: usize,
+}
+
+impl<'a, M> Transaction<'a, M>
+where
+    M: MetricAggregate,
+{
+    pub(super) fn new(measurer: &'a mut MetricMeasurer<M>, metric: M::Metric) -> Self {
+        let depth = measurer.transactions.len() - 1;
+
+        Self {
+            measurer,
+            metric: Some(metric),
+            depth,
+        }
+    }
+
+    /// Completes transaction and records its latency
+    ///
+    /// Transaction fails with [`MetricRecordError::Transaction`] if any of its children failed
+    pub fn end(mut self) -> Result<(), MetricRecordError> {
+        self.complete()
+    }
+
+    fn complete(&mut self) -> Result<(), MetricRecordError> {
+        let Some(metric) = self.metric.take() else {
+            return Ok(());
+        };
+
+        self.measurer.transactions.truncate(self.depth + 1);
+        let Some(transaction) = self.measurer.transactions.pop() else {
+            return Ok(());
+        };
+
+        let result = match transaction.failed {
+            Some(child) => Err(MetricRecordError::Transaction(child)),
+            None => Ok(()),
+        };
+
+        self.measurer
+            .add_measurement(metric, transaction.start.elapsed(), result.as_ref().err());
+
+        result
+    }
+}
+
+impl<M> Deref for Transaction<'_, M>
+where
+    M: MetricAggregate,
+{
+    type Target = MetricMeasurer<M>;
+
+    fn deref(&self) -> &Self::Target {
+        self.measurer
+    }
+}
+
+impl<M> DerefMut for Transaction<'_, M>
+where
+    M: MetricAggregate,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.measurer
+    }
+}
+
+impl<M> Drop for Transaction<'_, M>
+where
+    M: MetricAggregate,
+{
+    fn drop(&mut self) {
+        let _ = self.complete();
+    }
+}
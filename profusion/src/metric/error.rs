@@ -11,6 +11,9 @@ pub enum MetricRecordError {
     #[error("Operation has reached maximum time limit {0:?}")]
     Timeout(Duration),
 
+    #[error("Transaction has failed due to failure of {0} operation")]
+    Transaction(String),
+
     // Allows returning any error from that supports Error trait
     #[error(transparent)]
     Dynamic(#[from] Box<dyn Error>),
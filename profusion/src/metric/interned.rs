@@ -0,0 +1,173 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::{OnceLock, RwLock};
+
+use rustc_hash::FxHashSet;
+
+use crate::metric::Metric;
+
+/// Metric with a name known only at runtime, e.g. URL template or tenant
+///
+/// Cheap `Copy` handle of a name stored once for the lifetime of the process in a global interner.
+/// Name is resolved without the interner, handles are compared by identity of their names
+/// and ordered by the names themselves.
+#[derive(Copy, Clone)]
+pub struct InternedMetric(&'static str);
+
+fn interner() -> &'static RwLock<FxHashSet<&'static str>> {
+    static INTERNER: OnceLock<RwLock<FxHashSet<&'static str>>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl InternedMetric {
+    /// Returns handle for a name, interning it on first use
+    pub fn new(name: impl AsRef<str>) -> Self {
+        let name = name.as_ref();
+
+        if let Some(name) = interner().read().unwrap_or_else(|error| error.into_inner()).get(name) {
+            return Self(name);
+        }
+
+        let mut interner = interner().write().unwrap_or_else(|error| error.into_inner());
+
+        if let Some(name) = interner.get(name) {
+            return Self(name);
+        }
+
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        interner.insert(name);
+
+        Self(name)
+    }
+}
+
+impl Metric for InternedMetric {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl PartialEq for InternedMetric {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for InternedMetric {}
+
+impl Hash for InternedMetric {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0, state)
+    }
+}
+
+impl PartialOrd for InternedMetric {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InternedMetric {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+impl From<&str> for InternedMetric {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for InternedMetric {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Debug for InternedMetric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("InternedMetric").field(&self.name()).finish()
+    }
+}
+
+impl Display for InternedMetric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregate::{AggregateStorage, MetricAggregateStorage};
+
+    use super::*;
+
+    #[test]
+    fn returns_same_handle_for_same_name() {
+        let endpoint = format!("/api/{}", "products");
+
+        assert_eq!(
+            InternedMetric::new(&endpoint),
+            InternedMetric::from("/api/products")
+        );
+        assert_ne!(
+            InternedMetric::new("/api/products"),
+            InternedMetric::new("/api/orders")
+        );
+    }
+
+    #[test]
+    fn resolves_name_of_metric() {
+        let metric = InternedMetric::from(String::from("tenant_one"));
+
+        assert_eq!(metric.name(), "tenant_one");
+        assert_eq!(metric.to_string(), "tenant_one");
+        assert_eq!(format!("{metric:?}"), "InternedMetric(\"tenant_one\")");
+    }
+
+    #[test]
+    fn interns_names_from_multiple_threads() {
+        let handles = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..100)
+                        .map(|index| InternedMetric::new(format!("thread_metric_{index}")))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>();
+
+        for metrics in &handles[1..] {
+            assert_eq!(metrics, &handles[0]);
+        }
+        assert_eq!(handles[0][42].name(), "thread_metric_42");
+    }
+
+    #[test]
+    fn orders_metrics_by_name() {
+        let (zeta, alpha) = (
+            InternedMetric::new("zeta_metric"),
+            InternedMetric::new("alpha_metric"),
+        );
+
+        let mut metrics = vec![zeta, alpha];
+        metrics.sort();
+
+        assert_eq!(metrics, vec![alpha, zeta]);
+    }
+
+    #[test]
+    fn can_be_used_as_key_in_aggregate_storage() {
+        let mut storage = MetricAggregateStorage::default();
+
+        storage.record(InternedMetric::new("/cart/{id}"), 100);
+        storage.record(InternedMetric::new("/cart/{id}"), 200);
+
+        assert_eq!(storage.value(InternedMetric::new("/cart/{id}")).len(), 2);
+    }
+}
@@ -1,11 +1,24 @@
 pub use std::hash::Hash;
 
 pub use error::*;
+pub use interned::*;
+pub use tagged::*;
+pub use tree::*;
+pub use value::*;
 
 mod error;
+mod interned;
+mod tagged;
+mod tree;
+mod value;
 
 pub trait Metric: Hash + Eq + Copy {
     fn name(&self) -> &str;
+
+    /// Key-value pairs for slicing reports, e.g. region or status class
+    fn tags(&self) -> &[(&str, &str)] {
+        &[]
+    }
 }
 
 impl Metric for &str {
@@ -21,6 +34,7 @@ mod tests {
     use super::*;
 
     #[derive(Hash, PartialEq, Eq, Debug, Copy, Clone)]
+    #[allow(clippy::enum_variant_names)]
     enum TestMetric {
         ConnectionTime,
         RequestTime,
@@ -44,6 +58,11 @@ mod tests {
         assert_eq!("name", "name".name());
     }
 
+    #[test]
+    fn has_no_tags_by_default() {
+        assert!(TestMetric::RequestTime.tags().is_empty());
+    }
+
     #[test]
     fn verify_metric_can_be_used_as_hashmap_key() {
         let mut map = HashMap::new();
@@ -0,0 +1,375 @@
+use std::sync::{OnceLock, RwLock};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use tracing::warn;
+
+use crate::metric::Metric;
+
+type TagPairs = &'static [(&'static str, &'static str)];
+
+/// Maximum number of distinct tag keys kept for the lifetime of the process
+///
+/// Keys above the limit are replaced with [`OVERFLOW_TAG_KEY`] with [`OVERFLOW_TAG_VALUE`].
+pub const MAX_TAG_KEYS: usize = 64;
+
+/// Maximum number of distinct values of each tag key kept for the lifetime of the process
+///
+/// Values above the limit of their key are replaced with [`OVERFLOW_TAG_VALUE`],
+/// so unbounded values like user or request ids cannot exhaust memory,
+/// while values of other keys are not affected.
+pub const MAX_TAG_VALUES: usize = 1_000;
+
+/// Tag key reported instead of keys above [`MAX_TAG_KEYS`]
+pub const OVERFLOW_TAG_KEY: &str = "other";
+
+/// Tag value reported instead of values above [`MAX_TAG_VALUES`] of a key
+pub const OVERFLOW_TAG_VALUE: &str = "other";
+
+/// Interned set of key-value tags sorted by key
+///
+/// Meant to be built once, e.g. per status class or region, and reused
+/// via [`TaggedMetric::with_tags`], as building it interns the tags
+/// in a global registry.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Default)]
+pub struct TagSet(TagPairs);
+
+/// Metric with a set of key-value tags, e.g. region, endpoint or status class
+///
+/// Each distinct tag set is stored separately in aggregates,
+/// use [`TimelineItem::rollup`](crate::aggregate::TimelineItem::rollup)
+/// to aggregate across tag values. Tags are interned for the lifetime of the process
+/// and limited by [`MAX_TAG_KEYS`] and [`MAX_TAG_VALUES`].
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub struct TaggedMetric<M> {
+    metric: M,
+    tags: TagSet,
+}
+
+/// Interned tag keys with their values and tag sets built from them
+struct TagRegistry {
+    max_keys: usize,
+    max_values: usize,
+    keys: FxHashMap<&'static str, TagValues>,
+    sets: FxHashSet<TagPairs>,
+    keys_collapsed: bool,
+}
+
+#[derive(Default)]
+struct TagValues {
+    values: FxHashSet<&'static str>,
+    collapsed: bool,
+}
+
+fn registry() -> &'static RwLock<TagRegistry> {
+    static REGISTRY: OnceLock<RwLock<TagRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(TagRegistry::new(MAX_TAG_KEYS, MAX_TAG_VALUES)))
+}
+
+/// Tags with a tag added or replaced, sorted by key
+fn with_tag(
+    tags: TagPairs,
+    key: &'static str,
+    value: &'static str,
+) -> Vec<(&'static str, &'static str)> {
+    let mut pairs: Vec<_> = tags.iter().copied().filter(|(existing, _)| *existing != key).collect();
+    pairs.push((key, value));
+    pairs.sort_unstable();
+    pairs
+}
+
+impl TagRegistry {
+    fn new(max_keys: usize, max_values: usize) -> Self {
+        Self {
+            max_keys,
+            max_values,
+            keys: FxHashMap::default(),
+            sets: FxHashSet::default(),
+            keys_collapsed: false,
+        }
+    }
+
+    /// Interned tag set with a tag added or replaced, when the tag is already known
+    fn find(&self, tags: TagPairs, key: &str, value: &str) -> Option<TagPairs> {
+        let (key, values) = self.keys.get_key_value(key)?;
+        let value = values.values.get(value)?;
+
+        self.sets.get(with_tag(tags, key, value).as_slice()).copied()
+    }
+
+    /// Tag set with a tag added or replaced, interned on first use
+    ///
+    /// Key and value are interned only within the limits, otherwise they are collapsed
+    /// into overflow ones with a warning on the first collapse
+    fn tag_set(&mut self, tags: TagPairs, key: &str, value: &str) -> TagPairs {
+        let (key, value) = match self.intern_key(key) {
+            Some(key) => (key, self.intern_value(key, value)),
+            None => (OVERFLOW_TAG_KEY, OVERFLOW_TAG_VALUE),
+        };
+
+        let pairs = with_tag(tags, key, value);
+        match self.sets.get(pairs.as_slice()) {
+            Some(tags) => tags,
+            None => {
+                let tags: TagPairs = Box::leak(pairs.into_boxed_slice());
+                self.sets.insert(tags);
+                tags
+            }
+        }
+    }
+
+    fn intern_key(&mut self, key: &str) -> Option<&'static str> {
+        if let Some((key, _)) = self.keys.get_key_value(key) {
+            return Some(key);
+        }
+
+        if self.keys.len() >= self.max_keys {
+            if !self.keys_collapsed {
+                self.keys_collapsed = true;
+                warn!(
+                    key,
+                    limit = self.max_keys,
+                    "Tag keys above limit are reported as {OVERFLOW_TAG_KEY}"
+                );
+            }
+            return None;
+        }
+
+        let key: &'static str = Box::leak(key.into());
+        self.keys.insert(key, TagValues::default());
+        Some(key)
+    }
+
+    fn intern_value(&mut self, key: &'static str, value: &str) -> &'static str {
+        let max_values = self.max_values;
+        let Some(values) = self.keys.get_mut(key) else {
+            return OVERFLOW_TAG_VALUE;
+        };
+
+        if let Some(value) = values.values.get(value) {
+            return value;
+        }
+
+        if values.values.len() >= max_values {
+            if !values.collapsed {
+                values.collapsed = true;
+                warn!(
+                    key,
+                    limit = max_values,
+                    "Values of tag above limit are reported as {OVERFLOW_TAG_VALUE}"
+                );
+            }
+            return OVERFLOW_TAG_VALUE;
+        }
+
+        let value: &'static str = Box::leak(value.into());
+        values.values.insert(value);
+        value
+    }
+}
+
+/// Tag set with a tag added or replaced from a registry, interned on first use
+fn tag_set(registry: &RwLock<TagRegistry>, tags: TagPairs, key: &str, value: &str) -> TagPairs {
+    if let Some(tags) = registry
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .find(tags, key, value)
+    {
+        return tags;
+    }
+
+    registry
+        .write()
+        .unwrap_or_else(|error| error.into_inner())
+        .tag_set(tags, key, value)
+}
+
+impl TagSet {
+    pub fn new<K, V>(tags: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        tags.into_iter().fold(Self::default(), |tags, (key, value)| {
+            tags.with_tag(key, value)
+        })
+    }
+
+    /// Adds a tag, replacing the value of an existing tag with the same key
+    pub fn with_tag(self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        Self(tag_set(registry(), self.0, key.as_ref(), value.as_ref()))
+    }
+
+    pub fn tags(&self) -> &'static [(&'static str, &'static str)] {
+        self.0
+    }
+}
+
+impl<M> TaggedMetric<M>
+where
+    M: Metric,
+{
+    pub fn new(metric: M) -> Self {
+        Self {
+            metric,
+            tags: TagSet::default(),
+        }
+    }
+
+    /// Adds a tag, replacing the value of an existing tag with the same key
+    ///
+    /// Looks up the resulting tag set in a global registry, so handles are meant to be built
+    /// once and reused for measurements instead of adding tags on each of them
+    pub fn with_tag(self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        Self {
+            tags: self.tags.with_tag(key, value),
+            ..self
+        }
+    }
+
+    /// Same metric with a prebuilt tag set, replacing all existing tags
+    pub fn with_tags(self, tags: TagSet) -> Self {
+        Self { tags, ..self }
+    }
+
+    /// Same metric without any tags
+    pub fn without_tags(self) -> Self {
+        Self::new(self.metric)
+    }
+
+    pub fn metric(&self) -> M {
+        self.metric
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&'static str> {
+        self.tags
+            .0
+            .iter()
+            .find(|(existing, _)| *existing == key)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn has_tag(&self, key: &str, value: &str) -> bool {
+        self.tag(key) == Some(value)
+    }
+}
+
+impl<M> From<M> for TaggedMetric<M>
+where
+    M: Metric,
+{
+    fn from(value: M) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<M> Metric for TaggedMetric<M>
+where
+    M: Metric,
+{
+    fn name(&self) -> &str {
+        self.metric.name()
+    }
+
+    fn tags(&self) -> &[(&str, &str)] {
+        self.tags.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_name_of_underlying_metric() {
+        let metric = TaggedMetric::new("http_request").with_tag("status", "2xx");
+
+        assert_eq!(metric.name(), "http_request");
+        assert_eq!(metric.metric(), "http_request");
+    }
+
+    #[test]
+    fn sorts_tags_by_key() {
+        let metric = TaggedMetric::new("http_request")
+            .with_tag("status", "2xx")
+            .with_tag("region", "eu");
+
+        assert_eq!(metric.tags(), &[("region", "eu"), ("status", "2xx")]);
+    }
+
+    #[test]
+    fn equals_regardless_of_tag_order() {
+        let one = TaggedMetric::new("http_request")
+            .with_tag("status", "2xx")
+            .with_tag("region", "eu");
+        let two = TaggedMetric::new("http_request")
+            .with_tag("region", "eu")
+            .with_tag("status", "2xx");
+
+        assert_eq!(one, two);
+        assert_ne!(one, one.with_tag("status", "5xx"));
+    }
+
+    #[test]
+    fn replaces_value_of_existing_tag() {
+        let metric = TaggedMetric::new("http_request")
+            .with_tag("status", "2xx")
+            .with_tag("status", String::from("5xx"));
+
+        assert_eq!(metric.tags(), &[("status", "5xx")]);
+        assert!(metric.has_tag("status", "5xx"));
+        assert_eq!(metric.tag("region"), None);
+    }
+
+    #[test]
+    fn reuses_prebuilt_tag_set() {
+        let tags = TagSet::new([("status", "2xx"), ("region", "eu")]);
+        let metric = TaggedMetric::new("http_request").with_tag("method", "GET");
+
+        assert_eq!(tags.tags(), &[("region", "eu"), ("status", "2xx")]);
+        assert_eq!(
+            metric.with_tags(tags),
+            TaggedMetric::new("http_request")
+                .with_tag("region", "eu")
+                .with_tag("status", "2xx")
+        );
+    }
+
+    #[test]
+    fn replaces_values_above_limit_of_their_key() {
+        let registry = RwLock::new(TagRegistry::new(2, 2));
+
+        let first = tag_set(&registry, &[], "tenant", "one");
+        tag_set(&registry, first, "tenant", "two");
+        let third = tag_set(&registry, first, "tenant", "three");
+        let status = tag_set(&registry, third, "status", "5xx");
+
+        assert_eq!(third, &[("tenant", OVERFLOW_TAG_VALUE)]);
+        assert_eq!(status, &[("status", "5xx"), ("tenant", OVERFLOW_TAG_VALUE)]);
+        assert_eq!(
+            tag_set(&registry, &[], "tenant", "two"),
+            &[("tenant", "two")]
+        );
+    }
+
+    #[test]
+    fn replaces_keys_above_limit() {
+        let registry = RwLock::new(TagRegistry::new(1, 10));
+
+        let status = tag_set(&registry, &[], "status", "2xx");
+        let tags = tag_set(&registry, status, "request_id", "42");
+
+        assert_eq!(
+            tags,
+            &[(OVERFLOW_TAG_KEY, OVERFLOW_TAG_VALUE), ("status", "2xx")]
+        );
+        assert!(!registry.read().unwrap().keys.contains_key("request_id"));
+    }
+
+    #[test]
+    fn removes_all_tags() {
+        let metric = TaggedMetric::new("http_request").with_tag("status", "2xx");
+
+        assert_eq!(metric.without_tags(), TaggedMetric::from("http_request"));
+        assert!(metric.without_tags().tags().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+/// Separator between segments of hierarchical metric names, e.g. `load_test_one::one`
+pub const METRIC_SEPARATOR: &str = "::";
+
+/// Hierarchy of metric names split by [`METRIC_SEPARATOR`]
+///
+/// Root node has an empty path, each child node covers all metrics starting with its path
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MetricTree {
+    path: String,
+    children: Vec<MetricTree>,
+}
+
+impl MetricTree {
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut root = Self::default();
+
+        for name in names {
+            root.insert(name);
+        }
+
+        root
+    }
+
+    fn insert(&mut self, name: &str) {
+        let mut node = self;
+        let mut end = 0;
+
+        for segment in name.split(METRIC_SEPARATOR) {
+            end += if end == 0 {
+                segment.len()
+            } else {
+                METRIC_SEPARATOR.len() + segment.len()
+            };
+
+            let path = &name[..end];
+            let position =
+                match node.children.binary_search_by(|child| child.path.as_str().cmp(path)) {
+                    Ok(position) => position,
+                    Err(position) => {
+                        node.children.insert(
+                            position,
+                            Self {
+                                path: path.to_owned(),
+                                children: Vec::new(),
+                            },
+                        );
+                        position
+                    }
+                };
+
+            node = &mut node.children[position];
+        }
+    }
+
+    /// Full name of the node, e.g. `load_test_one::one`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Last segment of the name, e.g. `one` for `load_test_one::one`
+    pub fn name(&self) -> &str {
+        self.path.rsplit(METRIC_SEPARATOR).next().unwrap_or_default()
+    }
+
+    pub fn children(&self) -> &[MetricTree] {
+        &self.children
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/// Checks whether metric name is equal to the path or is nested under it
+pub fn is_nested_metric(name: &str, path: &str) -> bool {
+    match name.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || rest.starts_with(METRIC_SEPARATOR),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_tree_from_hierarchical_names() {
+        let tree = MetricTree::from_names([
+            "load_test_two::one",
+            "load_test_one::two",
+            "load_test_one::one",
+            "standalone",
+        ]);
+
+        let paths: Vec<_> = tree.children().iter().map(MetricTree::path).collect();
+        assert_eq!(paths, ["load_test_one", "load_test_two", "standalone"]);
+
+        let nested: Vec<_> = tree.children()[0]
+            .children()
+            .iter()
+            .map(|node| (node.path(), node.name()))
+            .collect();
+        assert_eq!(
+            nested,
+            [("load_test_one::one", "one"), ("load_test_one::two", "two")]
+        );
+        assert!(tree.children()[2].is_leaf());
+    }
+
+    #[test]
+    fn merges_repeated_names_into_same_node() {
+        let tree = MetricTree::from_names(["one::two", "one::two", "one"]);
+
+        assert_eq!(tree.children().len(), 1);
+        assert_eq!(tree.children()[0].children().len(), 1);
+    }
+
+    #[test]
+    fn matches_nested_metrics_by_whole_segments() {
+        assert!(is_nested_metric("load_test_one::one", "load_test_one"));
+        assert!(is_nested_metric("load_test_one", "load_test_one"));
+        assert!(!is_nested_metric("load_test_one_more", "load_test_one"));
+        assert!(!is_nested_metric("load_test_two::one", "load_test_one"));
+    }
+}
@@ -0,0 +1,45 @@
+/// Value recorded for a metric in addition to latency of operations
+///
+/// Values are recorded as-is without [`AggregateScale`](crate::aggregate::AggregateScale)
+/// and are not counted as operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricValue {
+    /// Increment of a counter, e.g. cache hits, summed up within a time window
+    Counter(u64),
+    /// Sample of a current level, e.g. queue depth, aggregated into minimum, maximum and mean
+    Gauge(u64),
+    /// Arbitrary value which distribution matters, e.g. items in a cart
+    Distribution(u64),
+    /// Size of transferred data, aggregated as a distribution
+    Bytes(u64),
+}
+
+/// Kind of [`MetricValue`], so storages can keep values of different kinds apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricValueKind {
+    Counter,
+    Gauge,
+    Distribution,
+    Bytes,
+}
+
+impl MetricValue {
+    /// Raw value to be stored in aggregate storage
+    pub fn value(&self) -> u64 {
+        match self {
+            Self::Counter(value)
+            | Self::Gauge(value)
+            | Self::Distribution(value)
+            | Self::Bytes(value) => *value,
+        }
+    }
+
+    pub fn kind(&self) -> MetricValueKind {
+        match self {
+            Self::Counter(_) => MetricValueKind::Counter,
+            Self::Gauge(_) => MetricValueKind::Gauge,
+            Self::Distribution(_) => MetricValueKind::Distribution,
+            Self::Bytes(_) => MetricValueKind::Bytes,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+/// Small deterministic pseudo random generator (SplitMix64)
+///
+/// Used for load distribution decisions where reproducibility
+/// of a run matters more than statistical quality
+#[derive(Debug, Clone)]
+pub(crate) struct Random(u64);
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Independent generator for a stream, e.g. a virtual user, derived from the seed
+    pub(crate) fn for_stream(seed: u64, stream: u64) -> Self {
+        Self::new(Self::new(seed ^ stream.wrapping_mul(GOLDEN_GAMMA)).next_u64())
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GOLDEN_GAMMA);
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    /// Value in range of `0..bound`, returns 0 for zero bound
+    pub(crate) fn next_below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Value in range of `0.0..1.0`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_same_sequence_for_same_seed() {
+        let (mut one, mut two) = (Random::new(42), Random::new(42));
+
+        for _ in 0..100 {
+            assert_eq!(one.next_u64(), two.next_u64());
+        }
+    }
+
+    #[test]
+    fn generates_different_sequences_for_streams() {
+        let (mut one, mut two) = (Random::for_stream(42, 0), Random::for_stream(42, 1));
+
+        assert_ne!(one.next_u64(), two.next_u64());
+    }
+
+    #[test]
+    fn keeps_values_within_bounds() {
+        let mut random = Random::new(7);
+
+        for _ in 0..1000 {
+            assert!(random.next_below(10) < 10);
+            assert!((0.0..1.0).contains(&random.next_f64()));
+        }
+
+        assert_eq!(random.next_below(0), 0);
+    }
+}
@@ -0,0 +1,550 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use thiserror::Error;
+
+use crate::aggregate::MetricAggregate;
+use crate::measurer::MetricMeasurer;
+use crate::metric::{Metric, MetricRecordError};
+use crate::random::Random;
+
+use super::{Scenario, ScenarioBuilder};
+
+/// Default seed of the scenario selection, so runs are reproducible unless specified otherwise
+const DEFAULT_SEED: u64 = 0x5EED;
+
+/// Scenario mix where weights of all scenarios are zero, so no scenario can be selected
+#[derive(Error, Debug)]
+#[error("scenario mix requires at least one non-zero weight")]
+pub struct EmptyMixError;
+
+/// When scenario of the mix is selected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MixSelection {
+    /// Each iteration of a virtual user picks a scenario by weights
+    #[default]
+    PerIteration,
+    /// Each virtual user picks a single scenario by weights on creation
+    PerUser,
+}
+
+/// Scenario builder that combines several builders with weights
+///
+/// All scenarios record into the same aggregate, so totals reflect the whole mix.
+/// Weights are relative, e.g. `ScenarioMix::new(browse, 70).with(search, 20).with(checkout, 10)`.
+/// Each virtual user of the mix samples think times from its own stream of the measurer seed.
+pub struct ScenarioMix<E> {
+    entries: E,
+    selection: MixSelection,
+    seed: u64,
+    next_user: AtomicU64,
+}
+
+/// Builder within a scenario mix followed by the rest of the mix
+pub struct MixEntry<B, R> {
+    builder: B,
+    weight: u64,
+    rest: R,
+}
+
+/// Scenarios of a virtual user within a mix
+pub struct MixedScenario<S, R> {
+    scenario: Option<S>,
+    weight: u64,
+    rest: R,
+}
+
+/// Scenario built by [`ScenarioMix`]
+pub struct ScenarioMixScenario<S> {
+    scenarios: S,
+    user: u64,
+    random: Random,
+    total_weight: u64,
+    selected: Option<u64>,
+}
+
+/// List of weighted builders within [`ScenarioMix`]
+#[allow(async_fn_in_trait)]
+pub trait MixEntries<T>
+where
+    T: Metric,
+{
+    type Scenarios: MixedScenarios<T>;
+
+    fn total_weight(&self) -> u64;
+
+    /// Builds every scenario with non-zero weight for selection per iteration
+    fn build_all(&self) -> Self::Scenarios;
+
+    /// Builds only the scenario at the weighted position
+    fn build_selected(&self, position: u64) -> Self::Scenarios;
+
+    async fn before_all(&self) -> Result<(), MetricRecordError>;
+
+    async fn after_all(&self) -> Result<(), MetricRecordError>;
+}
+
+/// List of scenarios of a virtual user within [`ScenarioMix`]
+#[allow(async_fn_in_trait)]
+pub trait MixedScenarios<T>
+where
+    T: Metric,
+{
+    async fn setup(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError>;
+
+    /// Executes the scenario at the weighted position
+    async fn execute(
+        &mut self,
+        position: u64,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError>;
+
+    async fn teardown(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError>;
+}
+
+impl<B> ScenarioMix<MixEntry<B, ()>> {
+    pub fn new(builder: B, weight: u32) -> Self {
+        Self {
+            entries: MixEntry {
+                builder,
+                weight: weight as u64,
+                rest: (),
+            },
+            selection: MixSelection::default(),
+            seed: DEFAULT_SEED,
+            next_user: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<E> ScenarioMix<E> {
+    /// Adds another builder into the mix
+    ///
+    /// Weights are relative to each other, so `70, 20, 10` and `7, 2, 1` produce the same mix
+    pub fn with<B>(self, builder: B, weight: u32) -> ScenarioMix<MixEntry<B, E>> {
+        ScenarioMix {
+            entries: MixEntry {
+                builder,
+                weight: weight as u64,
+                rest: self.entries,
+            },
+            selection: self.selection,
+            seed: self.seed,
+            next_user: self.next_user,
+        }
+    }
+
+    pub fn with_selection(self, selection: MixSelection) -> Self {
+        Self { selection, ..self }
+    }
+
+    /// Seed of the scenario selection, each virtual user gets its own sequence derived from it
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+}
+
+impl<T, E> ScenarioBuilder<T> for ScenarioMix<E>
+where
+    T: Metric,
+    E: MixEntries<T>,
+{
+    type Scenario = ScenarioMixScenario<E::Scenarios>;
+
+    /// Builds scenarios of a virtual user within the mix
+    ///
+    /// Virtual user of a mix without any weight executes nothing,
+    /// such mix is rejected by [`Self::before_all`]
+    fn build(&self) -> Self::Scenario {
+        let total_weight = self.entries.total_weight();
+
+        let user = self.next_user.fetch_add(1, Ordering::Relaxed);
+        let mut random = Random::for_stream(self.seed, user);
+
+        let (scenarios, selected) = match self.selection {
+            MixSelection::PerIteration => (self.entries.build_all(), None),
+            MixSelection::PerUser => {
+                let position = random.next_below(total_weight);
+                (self.entries.build_selected(position), Some(position))
+            }
+        };
+
+        ScenarioMixScenario {
+            scenarios,
+            user,
+            random,
+            total_weight,
+            selected,
+        }
+    }
+
+    /// Fails with [`EmptyMixError`] when weights of all scenarios in the mix are zero
+    async fn before_all(&self) -> Result<(), MetricRecordError> {
+        if self.entries.total_weight() == 0 {
+            return Err(MetricRecordError::Dynamic(Box::new(EmptyMixError)));
+        }
+
+        self.entries.before_all().await
+    }
+
+    async fn after_all(&self) -> Result<(), MetricRecordError> {
+        self.entries.after_all().await
+    }
+}
+
+impl<T, S> Scenario<T> for ScenarioMixScenario<S>
+where
+    T: Metric,
+    S: MixedScenarios<T>,
+{
+    async fn setup(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        aggregate.assign_stream(self.user);
+        self.scenarios.setup(aggregate).await
+    }
+
+    async fn execute(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        let position = match self.selected {
+            Some(position) => position,
+            None => self.random.next_below(self.total_weight),
+        };
+
+        self.scenarios.execute(position, aggregate).await
+    }
+
+    async fn teardown(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        self.scenarios.teardown(aggregate).await
+    }
+}
+
+impl<T, B, R> MixEntries<T> for MixEntry<B, R>
+where
+    T: Metric,
+    B: ScenarioBuilder<T>,
+    R: MixEntries<T>,
+{
+    type Scenarios = MixedScenario<B::Scenario, R::Scenarios>;
+
+    fn total_weight(&self) -> u64 {
+        self.weight + self.rest.total_weight()
+    }
+
+    fn build_all(&self) -> Self::Scenarios {
+        MixedScenario {
+            scenario: (self.weight > 0).then(|| self.builder.build()),
+            weight: self.weight,
+            rest: self.rest.build_all(),
+        }
+    }
+
+    fn build_selected(&self, position: u64) -> Self::Scenarios {
+        match position.checked_sub(self.weight) {
+            None => MixedScenario {
+                scenario: Some(self.builder.build()),
+                weight: self.weight,
+                rest: self.rest.build_selected(u64::MAX),
+            },
+            Some(position) => MixedScenario {
+                scenario: None,
+                weight: self.weight,
+                rest: self.rest.build_selected(position),
+            },
+        }
+    }
+
+    async fn before_all(&self) -> Result<(), MetricRecordError> {
+        self.builder.before_all().await?;
+        self.rest.before_all().await
+    }
+
+    async fn after_all(&self) -> Result<(), MetricRecordError> {
+        self.builder.after_all().await?;
+        self.rest.after_all().await
+    }
+}
+
+impl<T> MixEntries<T> for ()
+where
+    T: Metric,
+{
+    type Scenarios = ();
+
+    fn total_weight(&self) -> u64 {
+        0
+    }
+
+    fn build_all(&self) -> Self::Scenarios {}
+
+    fn build_selected(&self, _position: u64) -> Self::Scenarios {}
+
+    async fn before_all(&self) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+
+    async fn after_all(&self) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+}
+
+impl<T, S, R> MixedScenarios<T> for MixedScenario<S, R>
+where
+    T: Metric,
+    S: Scenario<T>,
+    R: MixedScenarios<T>,
+{
+    async fn setup(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        if let Some(scenario) = self.scenario.as_mut() {
+            scenario.setup(aggregate).await?;
+        }
+
+        self.rest.setup(aggregate).await
+    }
+
+    async fn execute(
+        &mut self,
+        position: u64,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        match (position.checked_sub(self.weight), self.scenario.as_mut()) {
+            (None, Some(scenario)) => scenario.execute(aggregate).await,
+            (None, None) => Ok(()),
+            (Some(position), _) => self.rest.execute(position, aggregate).await,
+        }
+    }
+
+    async fn teardown(
+        &mut self,
+        aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        if let Some(scenario) = self.scenario.as_mut() {
+            scenario.teardown(aggregate).await?;
+        }
+
+        self.rest.teardown(aggregate).await
+    }
+}
+
+impl<T> MixedScenarios<T> for ()
+where
+    T: Metric,
+{
+    async fn setup(
+        &mut self,
+        _aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+
+    async fn execute(
+        &mut self,
+        _position: u64,
+        _aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+
+    async fn teardown(
+        &mut self,
+        _aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use crate::aggregate::{MetricAggregateBuilder, TestAggregateBuilder};
+    use crate::think_time::ThinkTime;
+
+    use super::*;
+
+    #[derive(Eq, PartialEq, Hash, Clone, Copy)]
+    struct TestMetric;
+
+    impl Metric for TestMetric {
+        fn name(&self) -> &str {
+            "test"
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct CountingBuilder {
+        built: Arc<AtomicUsize>,
+        executed: Arc<AtomicUsize>,
+        setup: Arc<AtomicUsize>,
+    }
+
+    struct CountingScenario(CountingBuilder);
+
+    impl ScenarioBuilder<TestMetric> for CountingBuilder {
+        type Scenario = CountingScenario;
+
+        fn build(&self) -> Self::Scenario {
+            self.built.fetch_add(1, Ordering::Relaxed);
+            CountingScenario(self.clone())
+        }
+    }
+
+    impl Scenario<TestMetric> for CountingScenario {
+        async fn setup(
+            &mut self,
+            _aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = TestMetric>>,
+        ) -> Result<(), MetricRecordError> {
+            self.0.setup.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        async fn execute(
+            &mut self,
+            aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = TestMetric>>,
+        ) -> Result<(), MetricRecordError> {
+            self.0.executed.fetch_add(1, Ordering::Relaxed);
+            aggregate.measure(TestMetric, async {}).await
+        }
+    }
+
+    fn executions(builders: &[CountingBuilder]) -> Vec<usize> {
+        builders
+            .iter()
+            .map(|builder| builder.executed.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    async fn run<B: ScenarioBuilder<TestMetric>>(mix: &B, users: usize, iterations: usize) {
+        let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+
+        for _ in 0..users {
+            let mut scenario = mix.build();
+            scenario.setup(&mut measurer).await.unwrap();
+
+            for _ in 0..iterations {
+                scenario.execute(&mut measurer).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn selects_scenarios_per_iteration_by_weights() {
+        let builders: Vec<_> = (0..3).map(|_| CountingBuilder::default()).collect();
+        let mix = ScenarioMix::new(builders[0].clone(), 70)
+            .with(builders[1].clone(), 20)
+            .with(builders[2].clone(), 10);
+
+        run(&mix, 10, 1000).await;
+
+        let executions = executions(&builders);
+
+        assert_eq!(executions.iter().sum::<usize>(), 10000);
+        assert!((6700..7300).contains(&executions[0]), "{executions:?}");
+        assert!((1700..2300).contains(&executions[1]), "{executions:?}");
+        assert!((800..1200).contains(&executions[2]), "{executions:?}");
+        assert_eq!(builders[0].setup.load(Ordering::Relaxed), 10);
+    }
+
+    #[tokio::test]
+    async fn selects_single_scenario_per_user() {
+        let builders: Vec<_> = (0..2).map(|_| CountingBuilder::default()).collect();
+        let mix = ScenarioMix::new(builders[0].clone(), 1)
+            .with(builders[1].clone(), 1)
+            .with_selection(MixSelection::PerUser);
+
+        run(&mix, 100, 5).await;
+
+        let built: Vec<_> = builders
+            .iter()
+            .map(|builder| builder.built.load(Ordering::Relaxed))
+            .collect();
+
+        assert_eq!(built.iter().sum::<usize>(), 100);
+        assert_eq!(executions(&builders), vec![built[0] * 5, built[1] * 5]);
+        assert!(built[0] > 0 && built[1] > 0);
+    }
+
+    #[tokio::test]
+    async fn reproduces_selection_with_same_seed() {
+        let mut results = Vec::new();
+
+        for _ in 0..2 {
+            let builders: Vec<_> = (0..2).map(|_| CountingBuilder::default()).collect();
+            let mix = ScenarioMix::new(builders[0].clone(), 1)
+                .with(builders[1].clone(), 1)
+                .with_seed(42);
+
+            run(&mix, 3, 100).await;
+
+            results.push(executions(&builders));
+        }
+
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[tokio::test]
+    async fn skips_scenarios_with_zero_weight() {
+        let builders: Vec<_> = (0..2).map(|_| CountingBuilder::default()).collect();
+        let mix = ScenarioMix::new(builders[0].clone(), 0).with(builders[1].clone(), 5);
+
+        run(&mix, 2, 50).await;
+
+        assert_eq!(executions(&builders), vec![0, 100]);
+        assert_eq!(builders[0].built.load(Ordering::Relaxed), 0);
+        assert_eq!(builders[0].setup.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn assigns_think_time_stream_per_user() {
+        let mix = ScenarioMix::new(CountingBuilder::default(), 1);
+        let think_time = ThinkTime::Uniform {
+            min: Duration::ZERO,
+            max: Duration::from_secs(1000),
+        };
+        let mut elapsed = Vec::new();
+
+        for _ in 0..2 {
+            let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+            let mut scenario = ScenarioBuilder::<TestMetric>::build(&mix);
+            scenario.setup(&mut measurer).await.unwrap();
+
+            let start = Instant::now();
+            measurer.think(think_time).await;
+            elapsed.push(start.elapsed());
+        }
+
+        assert_ne!(elapsed[0], elapsed[1]);
+    }
+
+    #[tokio::test]
+    async fn rejects_mix_without_any_weight_before_run() {
+        let builders: Vec<_> = (0..2).map(|_| CountingBuilder::default()).collect();
+        let mix = ScenarioMix::new(builders[0].clone(), 0).with(builders[1].clone(), 0);
+
+        let result = ScenarioBuilder::<TestMetric>::before_all(&mix).await;
+        run(&mix, 1, 10).await;
+
+        assert!(
+            matches!(result, Err(MetricRecordError::Dynamic(error)) if error.is::<EmptyMixError>())
+        );
+        assert_eq!(executions(&builders), vec![0, 0]);
+    }
+}
@@ -3,6 +3,11 @@ use crate::measurer::MetricMeasurer;
 use crate::metric::MetricRecordError;
 use crate::prelude::Metric;
 
+pub use mix::*;
+
+mod mix;
+
+#[allow(async_fn_in_trait)]
 pub trait ScenarioBuilder<T>
 where
     T: Metric,
@@ -10,6 +15,16 @@ where
     type Scenario: Scenario<T>;
 
     fn build(&self) -> Self::Scenario;
+
+    /// Executed once before any virtual user is started
+    async fn before_all(&self) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+
+    /// Executed once after all virtual users are finished
+    async fn after_all(&self) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -17,14 +32,35 @@ pub trait Scenario<T>
 where
     T: Metric,
 {
+    /// Executed once per virtual user before the first iteration
+    ///
+    /// Time of the setup is not recorded unless it is measured
+    /// with provided measurer under its own metric
+    async fn setup(
+        &mut self,
+        _aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
+
     async fn execute(
         &mut self,
         aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
     ) -> Result<(), MetricRecordError>;
+
+    /// Executed once per virtual user after the last iteration
+    async fn teardown(
+        &mut self,
+        _aggregate: &mut MetricMeasurer<impl MetricAggregate<Metric = T>>,
+    ) -> Result<(), MetricRecordError> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::aggregate::{MetricAggregateBuilder, TestAggregateBuilder};
+
     use super::*;
 
     struct TestScenario;
@@ -46,4 +82,13 @@ mod tests {
             aggregate.measure(TestMetric, async {}).await
         }
     }
+
+    #[tokio::test]
+    async fn hooks_do_nothing_by_default() {
+        let mut measurer = MetricMeasurer::new(TestAggregateBuilder::new().build());
+        let mut scenario = TestScenario;
+
+        scenario.setup(&mut measurer).await.unwrap();
+        scenario.teardown(&mut measurer).await.unwrap();
+    }
 }
@@ -25,6 +25,11 @@ impl StartTime {
         }
     }
 
+    /// Time passed since the zero point
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.instant.elapsed()
+    }
+
     #[inline]
     pub fn window(&self, window: &Duration) -> Duration {
         let elapsed = self.instant.elapsed();
@@ -53,9 +58,9 @@ mod tests {
 
     use super::*;
 
-    const MILLISECOND: &'static Duration = &Duration::from_millis(1);
-    const TWO_SECONDS: &'static Duration = &Duration::from_secs(2);
-    const FIFTY_MILLISECONDS: &'static Duration = &Duration::from_millis(50);
+    const MILLISECOND: &Duration = &Duration::from_millis(1);
+    const TWO_SECONDS: &Duration = &Duration::from_secs(2);
+    const FIFTY_MILLISECONDS: &Duration = &Duration::from_millis(50);
 
     #[tokio::test(start_paused = true)]
     async fn returns_zero_for_not_passed_window_yet() {
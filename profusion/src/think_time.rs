@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use crate::random::Random;
+
+/// Pause of a virtual user between actions
+///
+/// Used via [`MetricMeasurer::think`](crate::measurer::MetricMeasurer::think),
+/// time spent thinking is not recorded as latency
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThinkTime {
+    /// Always the same pause
+    Fixed(Duration),
+    /// Pause evenly distributed between `min` and `max`
+    Uniform { min: Duration, max: Duration },
+    /// Normally distributed pause, negative samples are treated as no pause
+    Normal { mean: Duration, std_dev: Duration },
+    /// Exponentially distributed pause, e.g. for modelling arrivals of independent users
+    Exponential { mean: Duration },
+}
+
+impl ThinkTime {
+    pub(crate) fn sample(&self, random: &mut Random) -> Duration {
+        match self {
+            Self::Fixed(duration) => *duration,
+            Self::Uniform { min, max } if max > min => {
+                *min + (*max - *min).mul_f64(random.next_f64())
+            }
+            Self::Uniform { min, .. } => *min,
+            Self::Normal { mean, std_dev } => {
+                let (first, second) = (1.0 - random.next_f64(), random.next_f64());
+                let standard =
+                    (-2.0 * first.ln()).sqrt() * (2.0 * std::f64::consts::PI * second).cos();
+
+                Duration::from_secs_f64(
+                    (mean.as_secs_f64() + std_dev.as_secs_f64() * standard).max(0.0),
+                )
+            }
+            Self::Exponential { mean } => mean.mul_f64(-(1.0 - random.next_f64()).ln()),
+        }
+    }
+}
+
+impl From<Duration> for ThinkTime {
+    fn from(value: Duration) -> Self {
+        Self::Fixed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn average(think_time: ThinkTime, samples: u32) -> Duration {
+        let mut random = Random::new(1);
+
+        (0..samples)
+            .map(|_| think_time.sample(&mut random))
+            .sum::<Duration>()
+            / samples
+    }
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        let difference = actual.max(expected) - actual.min(expected);
+
+        assert!(
+            difference < expected / 20,
+            "{actual:?} is not close to {expected:?}"
+        );
+    }
+
+    #[test]
+    fn samples_fixed_think_time() {
+        let mut random = Random::new(1);
+
+        assert_eq!(
+            ThinkTime::Fixed(Duration::from_millis(100)).sample(&mut random),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn samples_uniform_think_time_within_range() {
+        let think_time = ThinkTime::Uniform {
+            min: Duration::from_millis(100),
+            max: Duration::from_millis(200),
+        };
+        let mut random = Random::new(1);
+
+        for _ in 0..1000 {
+            let value = think_time.sample(&mut random);
+            assert!(value >= Duration::from_millis(100) && value < Duration::from_millis(200));
+        }
+
+        assert_close(average(think_time, 10000), Duration::from_millis(150));
+    }
+
+    #[test]
+    fn samples_normal_think_time_around_mean() {
+        let think_time = ThinkTime::Normal {
+            mean: Duration::from_millis(500),
+            std_dev: Duration::from_millis(50),
+        };
+
+        assert_close(average(think_time, 10000), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn samples_exponential_think_time_around_mean() {
+        let think_time = ThinkTime::Exponential {
+            mean: Duration::from_millis(300),
+        };
+
+        assert_close(average(think_time, 10000), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn does_not_sample_negative_normal_think_time() {
+        let think_time = ThinkTime::Normal {
+            mean: Duration::ZERO,
+            std_dev: Duration::from_millis(50),
+        };
+        let mut random = Random::new(1);
+
+        let zeros = (0..1000)
+            .filter(|_| think_time.sample(&mut random).is_zero())
+            .count();
+
+        assert!(zeros > 400, "{zeros}");
+    }
+}